@@ -0,0 +1,159 @@
+//! Procedural macros for `file-backed-value`, which are re-exported by that crate when its `macros` feature is enabled.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{meta::ParseNestedMeta, parse_macro_input, Error, FnArg, ItemFn, LitStr, Pat, ReturnType};
+
+/// Persist the results of a function in file-backed values, keyed by its arguments.
+///
+/// The arguments must implement `Serialize`, and the return type both `Serialize` and `DeserializeOwned`.
+/// Results are stored in a subdirectory named after the function, in one file per distinct set of arguments.
+///
+/// ### Options
+/// - `dir = "..."`: directory in which the subdirectory of the function is created.
+///   Defaults to the user's cache directory, or the `cache` subdirectory of the `FILE_BACKED_VALUE_DIR` environment variable
+///   if it is set.
+/// - `ttl = "..."`: duration after which results are recomputed, such as `"90s"`, `"30m"`, `"1h30m"` or `"7d"`.
+///   Without a ttl, results are never recomputed.
+///
+/// If a result cannot be read or written, the function is simply evaluated, such that storage errors are never fatal.
+/// Note that a returned `Result::Err` is a result like any other, and is stored as well.
+///
+/// ### Example
+/// ```ignore
+/// #[file_backed(ttl = "1h")]
+/// fn fetch_report(year: u32, region: &str) -> Report {
+///     // ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn file_backed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut dir: Option<LitStr> = None;
+    let mut ttl: Option<u64> = None;
+    let parser = syn::meta::parser(|meta: ParseNestedMeta| {
+        if meta.path.is_ident("dir") {
+            dir = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("ttl") {
+            let lit: LitStr = meta.value()?.parse()?;
+            ttl = Some(parse_millis(&lit.value()).filter(|&millis| millis > 0).ok_or_else(|| Error::new(lit.span(),
+                "expected a non-zero duration such as \"90s\", \"30m\", \"1h30m\" or \"7d\""))?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `dir` or `ttl`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    match expand(function, dir, ttl) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(function: ItemFn, dir: Option<LitStr>, ttl: Option<u64>) -> syn::Result<proc_macro2::TokenStream> {
+    let ItemFn { attrs, vis, sig, block } = function;
+    if let Some(asyncness) = sig.asyncness {
+        return Err(Error::new_spanned(asyncness, "`file_backed` does not support async functions"));
+    }
+    if let Some(constness) = sig.constness {
+        return Err(Error::new_spanned(constness, "`file_backed` does not support const functions"));
+    }
+
+    let mut args = Vec::new();
+    for input in &sig.inputs {
+        match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => args.push(pat.ident.clone()),
+                pat => return Err(Error::new_spanned(pat, "`file_backed` requires arguments to be plain identifiers")),
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(receiver, "`file_backed` does not support methods"));
+            }
+        }
+    }
+
+    let output = match &sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let name = sig.ident.to_string();
+    let dir = match dir {
+        Some(dir) => quote!(::core::option::Option::Some(#dir)),
+        None => quote!(::core::option::Option::None),
+    };
+    let ttl = match ttl {
+        Some(millis) => quote!(::core::option::Option::Some(::std::time::Duration::from_millis(#millis))),
+        None => quote!(::core::option::Option::None),
+    };
+    // A tuple of references to the arguments, which is serialized to identify the result.
+    let key = quote!((#(&#args,)*));
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __file_backed_key = ::file_backed_value::__private::key(&#key);
+            ::file_backed_value::__private::memoize(
+                #dir,
+                ::core::concat!(::core::module_path!(), "::", #name),
+                #ttl,
+                __file_backed_key,
+                move || -> #output #block,
+            )
+        }
+    })
+}
+
+/// Parse a duration such as `"1h30m"` into milliseconds.
+fn parse_millis(duration: &str) -> Option<u64> {
+    let mut millis = 0u64;
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let amount: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let scale = match rest[..unit].trim() {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return None,
+        };
+        rest = &rest[unit..];
+
+        millis = millis.checked_add(amount.checked_mul(scale)?)?;
+    }
+
+    Some(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_millis;
+
+    #[test]
+    fn durations_are_parsed_into_milliseconds() {
+        assert_eq!(parse_millis("250ms"), Some(250));
+        assert_eq!(parse_millis("90s"), Some(90_000));
+        assert_eq!(parse_millis("1h30m"), Some(90 * 60 * 1000));
+        assert_eq!(parse_millis("7d"), Some(7 * 24 * 60 * 60 * 1000));
+        assert_eq!(parse_millis(" 1h 30m "), Some(90 * 60 * 1000));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        assert_eq!(parse_millis(""), None);
+        assert_eq!(parse_millis("10"), None);
+        assert_eq!(parse_millis("soon"), None);
+        assert_eq!(parse_millis("5w"), None);
+        assert_eq!(parse_millis("99999999999999999999d"), None);
+    }
+}
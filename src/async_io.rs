@@ -0,0 +1,356 @@
+use std::{convert::Infallible, future::Future, io, path::Path, time::{Duration, SystemTime}};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{fs, io::{AsyncReadExt, AsyncWriteExt}};
+
+use crate::{corruption, envelope::{Header, Stored}, flight::{AsyncFlight, Refresh}, inputs_changed, lock::FileLock, parent_dir, temp_path, FileBackedValue, FileBackedValueError, FileBackedValueResult, Format, LockPolicy, MaybeStale, RawFile, Repair, RepairAction, TryInsertError};
+
+/// Async versions of the accessors, using non-blocking file IO.
+///
+/// These must be called from within a tokio runtime, as locks are acquired on its blocking thread pool.
+///
+/// Tasks that concurrently compute the same missing value are coalesced like threads are, but separately from them:
+/// a thread in `get_or_insert_with` and a task in `get_or_insert_with_async` may both compute the value of the same
+/// backing file. Use [`LockPolicy::HoldDuringCompute`] to have only one of them compute it.
+impl<Fmt> FileBackedValue<Fmt>
+where
+    Fmt: Format
+{
+    /// Get the current value, which might be None if the backing file does not yet exist.
+    pub async fn get_async<T>(&self) -> FileBackedValueResult<Option<T>>
+    where
+        T: DeserializeOwned
+    {
+        let Some(stored) = self.read_async().await? else {
+            return Ok(None);
+        };
+        if self.is_expired_async(&stored.header).await {
+            return Ok(None);
+        }
+
+        self.record_access_async().await;
+        Ok(Some(stored.value))
+    }
+
+    /// Get the current value, or insert the output of `default()` if the backing file does not exist or is dirty.
+    pub async fn get_or_insert_with_async<F, Fut, T>(&self, default: F) -> FileBackedValueResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+        T: DeserializeOwned + Serialize,
+    {
+        self.get_or_try_insert_with_async(|| async { Ok::<_, Infallible>(default().await) }).await
+            .map_err(TryInsertError::into_storage)
+    }
+
+    /// Get the current value, or insert the result of `default()` if the backing file does not exist or is dirty.
+    /// If `default()` returns an error, nothing is written and the error is returned.
+    ///
+    /// Only one task of this process computes the value at a time, but this is not coordinated with threads that
+    /// use the blocking accessors, unless the lock policy is [`LockPolicy::HoldDuringCompute`].
+    pub async fn get_or_try_insert_with_async<F, Fut, T, E>(&self, default: F) -> Result<T, TryInsertError<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        T: DeserializeOwned + Serialize,
+    {
+        if let Some(res) = self.get_async().await? {
+            return Ok(res);
+        }
+
+        let flight = AsyncFlight::of(&self.path);
+        let _flight = flight.join().await;
+        // Another task might have inserted the value while we were waiting for it.
+        if let Some(res) = self.get_async().await? {
+            return Ok(res);
+        }
+
+        if self.options.lock_policy != LockPolicy::HoldDuringCompute {
+            let res = default().await.map_err(TryInsertError::Compute)?;
+            self.insert_async(&res).await?;
+            return Ok(res);
+        }
+
+        let _lock = self.lock_async(FileLock::exclusive).await.map_err(FileBackedValueError::from)?;
+        // Another process might have inserted the value while we were waiting for the lock.
+        let (stored, repair) = self.read_unlocked_async().await?;
+        if let Some(repair) = repair {
+            self.repair_unlocked_async(repair, true).await?;
+        }
+        if let Some(stored) = stored
+            && !self.is_expired_async(&stored.header).await
+        {
+            self.record_access_async().await;
+            return Ok(stored.value);
+        }
+
+        let res = default().await.map_err(TryInsertError::Compute)?;
+        self.write_unlocked_async(&res, None).await?;
+        Ok(res)
+    }
+
+    /// Like [`FileBackedValue::get_or_try_insert_with_async`], but if `default()` fails while an expired value exists,
+    /// that value is returned as [`MaybeStale::Stale`] instead, provided that stale-if-error is enabled
+    /// and the value has not been expired for longer than the configured maximum staleness.
+    pub async fn get_or_try_refresh_with_async<F, Fut, T, E>(&self, default: F) -> Result<MaybeStale<T>, TryInsertError<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        T: DeserializeOwned + Serialize,
+    {
+        match self.get_or_try_insert_with_async(default).await {
+            Ok(res) => Ok(MaybeStale::Fresh(res)),
+            Err(TryInsertError::Compute(e)) => match self.get_stale_async(self.options.stale_if_error).await? {
+                Some(res) => Ok(MaybeStale::Stale(res)),
+                None => Err(TryInsertError::Compute(e)),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Like [`FileBackedValue::get_or_insert_with_async`], but if an expired value exists, that value is returned
+    /// immediately as [`MaybeStale::Stale`] while `default()` recomputes it in a background task,
+    /// provided that stale-while-revalidate is enabled and the value has not been expired for longer than
+    /// the configured maximum staleness.
+    ///
+    /// Errors that occur while writing the recomputed value in the background are ignored;
+    /// the next access will simply try again.
+    pub async fn get_or_revalidate_with_async<F, Fut, T>(&self, default: F) -> FileBackedValueResult<MaybeStale<T>>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+        T: DeserializeOwned + Serialize + Send + Sync + 'static,
+        Fmt: Clone + Send + Sync + 'static,
+    {
+        if let Some(res) = self.get_async().await? {
+            return Ok(MaybeStale::Fresh(res));
+        }
+
+        if let Some(res) = self.get_stale_async(self.options.stale_while_revalidate).await? {
+            // Only one task refreshes a value at a time, rather than one for every access while it is stale.
+            if let Some(refresh) = Refresh::start(&self.path) {
+                let this = self.clone();
+                tokio::spawn(async move {
+                    let _refresh = refresh;
+                    let _ = this.get_or_insert_with_async(default).await;
+                });
+            }
+            return Ok(MaybeStale::Stale(res));
+        }
+
+        self.get_or_insert_with_async(default).await.map(MaybeStale::Fresh)
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value.
+    pub async fn insert_async<T>(&self, value: &T) -> FileBackedValueResult<()>
+    where
+        T: Serialize
+    {
+        let _lock = self.lock_async(FileLock::exclusive).await?;
+        self.write_unlocked_async(value, None).await.map(|_| ())
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, and lets it expire after `ttl`.
+    /// A `ttl` too large to be represented does not expire the value.
+    pub async fn insert_with_ttl_async<T>(&self, value: &T, ttl: Duration) -> FileBackedValueResult<()>
+    where
+        T: Serialize
+    {
+        let _lock = self.lock_async(FileLock::exclusive).await?;
+        self.write_unlocked_async(value, SystemTime::now().checked_add(ttl)).await.map(|_| ())
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, and lets it expire at `expires`.
+    pub async fn insert_until_async<T>(&self, value: &T, expires: SystemTime) -> FileBackedValueResult<()>
+    where
+        T: Serialize
+    {
+        let _lock = self.lock_async(FileLock::exclusive).await?;
+        self.write_unlocked_async(value, Some(expires)).await.map(|_| ())
+    }
+
+    /// Take a lock on the backing file using `acquire` without blocking the executor, unless locking is disabled.
+    async fn lock_async(&self, acquire: fn(&Path) -> io::Result<FileLock>) -> io::Result<Option<FileLock>> {
+        if self.options.lock_policy == LockPolicy::Disabled {
+            return Ok(None);
+        }
+
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || acquire(&path))
+            .await
+            .map_err(io::Error::other)?
+            .map(Some)
+    }
+
+    /// Get the current value even if it is dirty, provided that it has not been dirty for longer than `max_staleness`.
+    /// If `max_staleness` is None, stale values are never returned.
+    async fn get_stale_async<T>(&self, max_staleness: Option<Duration>) -> FileBackedValueResult<Option<T>>
+    where
+        T: DeserializeOwned
+    {
+        if max_staleness.is_none() {
+            return Ok(None);
+        }
+
+        Ok(self.read_async().await?
+            .filter(|stored| self.options.is_acceptably_stale(&stored.header, max_staleness))
+            .map(|stored| stored.value))
+    }
+
+    /// Read the value and its metadata from the backing file, regardless of whether it is dirty.
+    async fn read_async<T>(&self) -> FileBackedValueResult<Option<Stored<T>>>
+    where
+        T: DeserializeOwned
+    {
+        let (stored, repair) = {
+            let _lock = self.lock_async(FileLock::shared).await?;
+            self.read_unlocked_async().await?
+        };
+
+        if let Some(repair) = repair {
+            let lock = self.lock_async(FileLock::exclusive).await?;
+            self.repair_unlocked_async(repair, lock.is_some()).await?;
+        }
+        Ok(stored)
+    }
+
+    /// Read the value and its metadata from the backing file, regardless of whether it is dirty,
+    /// along with the change to the backing file that reading it called for, if any.
+    /// The caller is responsible for locking, and for applying the repair under an exclusive lock.
+    async fn read_unlocked_async<T>(&self) -> FileBackedValueResult<(Option<Stored<T>>, Option<Repair>)>
+    where
+        T: DeserializeOwned
+    {
+        let Some(raw) = read_raw_async(&self.path).await? else {
+            return Ok((None, None));
+        };
+
+        self.decode_with_repair(raw)
+    }
+
+    /// Apply `repair` to the backing file, provided that it still has the contents that were read.
+    /// Like its blocking counterpart, migrated values are only rewritten while holding the exclusive lock.
+    async fn repair_unlocked_async(&self, repair: Repair, locked: bool) -> FileBackedValueResult<()> {
+        if !repair.applies(locked) || read_raw_async(&self.path).await?.is_none_or(|raw| raw.bytes != repair.read) {
+            return Ok(());
+        }
+
+        match repair.action {
+            RepairAction::Rewrite(bytes) => write_bytes_async(&self.path, &bytes).await,
+            RepairAction::Quarantine => match fs::rename(&self.path, corruption::quarantine_path(&self.path)).await {
+                // Another reader might have moved the file aside already.
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// Write `value` to the backing file, and return the metadata that was written with it.
+    /// The caller is responsible for locking.
+    async fn write_unlocked_async<T>(&self, value: &T, expires: Option<SystemTime>) -> FileBackedValueResult<Header>
+    where
+        T: Serialize + ?Sized
+    {
+        let (header, bytes) = self.encode(value, expires, self.inputs_digest_async().await?)?;
+        write_bytes_async(&self.path, &bytes).await?;
+        if let Some(store) = self.options.store.clone() {
+            let path = self.path.clone();
+            tokio::task::spawn_blocking(move || store.record_write(&path))
+                .await
+                .map_err(io::Error::other)??;
+        }
+        Ok(header)
+    }
+
+    /// Whether a value with the given metadata is dirty, hashing its input files without blocking the executor.
+    async fn is_expired_async(&self, header: &Header) -> bool {
+        self.options.is_expired_regardless_of_inputs(header)
+            || inputs_changed(header, self.inputs_digest_async().await)
+    }
+
+    /// Digest of the current contents of the input files, or None if there are none, without blocking the executor.
+    async fn inputs_digest_async(&self) -> io::Result<Option<String>> {
+        if self.options.inputs.is_empty() {
+            return Ok(None);
+        }
+
+        let inputs = self.options.inputs.clone();
+        tokio::task::spawn_blocking(move || inputs.digest().map(Some))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Record that the value was read in the store it belongs to, if any, without blocking the executor.
+    async fn record_access_async(&self) {
+        if let Some(store) = self.options.store.clone() {
+            let path = self.path.clone();
+            // Access times only decide what is evicted first, which is not worth failing a read over.
+            let _ = tokio::task::spawn_blocking(move || store.record_access(&path)).await;
+        }
+    }
+}
+
+/// Read the contents of the file at `path`, or None if it does not exist.
+async fn read_raw_async(path: &Path) -> FileBackedValueResult<Option<RawFile>> {
+    let mut file = match fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(FileBackedValueError::FileError(e)),
+    };
+
+    let metadata = file.metadata().await?;
+    let mut bytes = Vec::with_capacity(metadata.len().try_into().unwrap_or(0));
+    file.read_to_end(&mut bytes).await?;
+    Ok(Some(RawFile {
+        bytes,
+        modified: metadata.modified().or_else(|_| metadata.created()).ok(),
+    }))
+}
+
+/// Write `bytes` to the file at `path`.
+///
+/// Like its blocking counterpart, this writes to a temporary file which is then atomically renamed over `path`.
+async fn write_bytes_async(path: &Path, bytes: &[u8]) -> FileBackedValueResult<()> {
+    // Create parent directories if necessary.
+    let dir = parent_dir(path);
+    fs::create_dir_all(dir).await?;
+
+    let tmp_path = temp_path(path);
+    let res = match write_temp_file_async(&tmp_path, bytes).await {
+        Ok(()) => fs::rename(&tmp_path, path).await,
+        Err(e) => Err(e),
+    };
+    if res.is_err() {
+        // Do not leave partially written temporary files behind.
+        let _ = fs::remove_file(&tmp_path).await;
+    }
+    res?;
+
+    // Make sure the rename itself is persisted.
+    sync_dir_async(dir).await
+}
+
+/// Write `bytes` to a new file at `tmp_path`, and flush it to disk.
+async fn write_temp_file_async(tmp_path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(tmp_path)
+        .await?;
+
+    file.write_all(bytes).await?;
+    file.sync_all().await
+}
+
+/// Flush the directory entries of `dir` to disk.
+#[cfg(unix)]
+async fn sync_dir_async(dir: &Path) -> FileBackedValueResult<()> {
+    fs::File::open(dir).await?.sync_all().await?;
+    Ok(())
+}
+
+/// Directories cannot be opened as files on this platform, so there is nothing to sync.
+#[cfg(not(unix))]
+async fn sync_dir_async(_dir: &Path) -> FileBackedValueResult<()> {
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use std::{path::{Path, PathBuf}, sync::Arc, time::Duration};
+
+use crate::{inputs::Inputs, location, BaseDir, Checksum, Compression, CorruptionPolicy, ExpiryPolicy, FileBackedStore, FileBackedValue, FileBackedValueError, FileBackedValueResult, Format, Json, LockPolicy, Options, Project};
+
+/// Builder for a [`FileBackedValue`], created through [`FileBackedValue::builder`].
+#[derive(Clone, Debug)]
+pub struct FileBackedValueBuilder<Fmt = Json> {
+    filename: String,
+    dir: Option<PathBuf>,
+    base_dir: BaseDir,
+    project: Option<Project>,
+    format: Fmt,
+    options: Options,
+}
+
+impl FileBackedValueBuilder {
+    pub(crate) fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_owned(),
+            dir: None,
+            base_dir: BaseDir::Data,
+            project: None,
+            format: Json,
+            options: Options::default(),
+        }
+    }
+}
+
+impl<Fmt> FileBackedValueBuilder<Fmt>
+where
+    Fmt: Format
+{
+    /// Store the backing file in `dir`, instead of in the user's data directory.
+    /// This takes precedence over the [`DIR_ENV_VAR`](crate::DIR_ENV_VAR) environment variable.
+    pub fn dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Store the backing file in the given kind of standard directory, instead of in the user's data directory.
+    pub fn base_dir(mut self, base_dir: BaseDir) -> Self {
+        self.base_dir = base_dir;
+        self
+    }
+
+    /// Store the backing file in a subdirectory of the base directory that is specific to `project`.
+    pub fn project(mut self, project: Project) -> Self {
+        self.project = Some(project);
+        self
+    }
+
+    /// Set the duration after which the file is considered dirty and needs to be recomputed.
+    pub fn dirty_time(mut self, dirty_time: Duration) -> Self {
+        self.options.dirty_time = Some(dirty_time);
+        self
+    }
+
+    /// Set the policy that decides when the file is considered dirty and needs to be recomputed.
+    /// If a dirty time is set as well, values expire at whichever comes first.
+    pub fn expiry(mut self, policy: impl ExpiryPolicy + 'static) -> Self {
+        self.options.expiry = Some(Arc::new(policy));
+        self
+    }
+
+    /// Register the files that the value is derived from.
+    /// A digest of their contents is stored alongside the value, and the value is dirty whenever they change.
+    pub fn inputs<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.options.inputs = Inputs::new(paths.into_iter().map(|path| path.as_ref().to_path_buf()).collect());
+        self
+    }
+
+    /// Set when to take advisory locks on the backing file, to coordinate access between processes.
+    pub fn lock_policy(mut self, lock_policy: LockPolicy) -> Self {
+        self.options.lock_policy = lock_policy;
+        self
+    }
+
+    /// Store a checksum of the serialized value, which is verified when it is read.
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.options.checksum = Some(checksum);
+        self
+    }
+
+    /// Compress the backing file using `compression`.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.options.compression = Some(compression);
+        self
+    }
+
+    /// Encrypt the backing file with XChaCha20-Poly1305, using the key supplied by `key_provider`.
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, key_provider: impl crate::KeyProvider + 'static) -> Self {
+        self.options.encryption = Some(crate::encryption::Encryption::new(key_provider));
+        self
+    }
+
+    /// Set what to do when the backing file cannot be deserialized.
+    pub fn corruption_policy(mut self, corruption_policy: CorruptionPolicy) -> Self {
+        self.options.corruption_policy = corruption_policy;
+        self
+    }
+
+    /// Keep expired values around, such that `get_or_try_refresh_with` can still return them if recomputing fails,
+    /// as long as they expired at most `max_staleness` ago. Requires a dirty time or expiry policy.
+    pub fn stale_if_error(mut self, max_staleness: Duration) -> Self {
+        self.options.stale_if_error = Some(max_staleness);
+        self
+    }
+
+    /// Let `get_or_revalidate_with` return expired values immediately while they are recomputed in the background,
+    /// as long as they expired at most `max_staleness` ago. Requires a dirty time or expiry policy.
+    pub fn stale_while_revalidate(mut self, max_staleness: Duration) -> Self {
+        self.options.stale_while_revalidate = Some(max_staleness);
+        self
+    }
+
+    /// Attach the value to `store`, which evicts entries when its directory exceeds its limits.
+    /// Unless an explicit directory inside that of the store is given, the backing file is stored directly in it.
+    pub fn store(mut self, store: FileBackedStore) -> Self {
+        self.options.store = Some(store);
+        self
+    }
+
+    /// Store the value using `format`, instead of JSON.
+    pub fn format<NewFmt: Format>(self, format: NewFmt) -> FileBackedValueBuilder<NewFmt> {
+        FileBackedValueBuilder {
+            filename: self.filename,
+            dir: self.dir,
+            base_dir: self.base_dir,
+            project: self.project,
+            format,
+            options: self.options,
+        }
+    }
+
+    /// Set the schema version that is stored alongside new values.
+    /// See [`FileBackedValue::set_schema_version`] for how stored values of other schema versions are handled.
+    pub fn schema_version(mut self, schema_version: u32) -> Self {
+        self.options.schema_version = schema_version;
+        self
+    }
+
+    /// Register `migrate`, which upgrades stored values of schema version `from` to version `from + 1`.
+    /// See [`FileBackedValue::add_migration`] for how values are migrated.
+    pub fn migration(mut self, from: u32, migrate: impl Fn(serde_json::Value) -> serde_json::Value + Send + Sync + 'static) -> Self {
+        self.options.migrations.insert(from, migrate);
+        self
+    }
+
+    /// Validate the configuration and create the file-backed value.
+    pub fn build(self) -> FileBackedValueResult<FileBackedValue<Fmt>> {
+        let filename = sanitize_filename::sanitize(&self.filename);
+        if filename.is_empty() {
+            return Err(FileBackedValueError::InvalidConfig(
+                format!("filename {:?} is empty after sanitization", self.filename)));
+        }
+
+        if self.dir.is_some() && (self.base_dir != BaseDir::Data || self.project.is_some()) {
+            return Err(FileBackedValueError::InvalidConfig(
+                "an explicit directory cannot be combined with a base directory or project".to_owned()));
+        }
+
+        if self.options.dirty_time.is_some_and(|dirty_time| dirty_time.is_zero()) {
+            return Err(FileBackedValueError::InvalidConfig(
+                "dirty time must be greater than zero".to_owned()));
+        }
+
+        if let Some(policy) = &self.options.expiry {
+            policy.validate()
+                .map_err(|e| FileBackedValueError::InvalidConfig(format!("invalid expiry policy: {e}")))?;
+        }
+
+        if self.options.stale_if_error.is_some() && self.options.dirty_time.is_none() && self.options.expiry.is_none() {
+            return Err(FileBackedValueError::InvalidConfig(
+                "stale-if-error requires a dirty time or expiry policy, as values never expire otherwise".to_owned()));
+        }
+
+        if self.options.stale_while_revalidate.is_some() && self.options.dirty_time.is_none() && self.options.expiry.is_none() {
+            return Err(FileBackedValueError::InvalidConfig(
+                "stale-while-revalidate requires a dirty time or expiry policy, as values never expire otherwise".to_owned()));
+        }
+
+        if let Some(store) = &self.options.store {
+            if self.base_dir != BaseDir::Data || self.project.is_some() {
+                return Err(FileBackedValueError::InvalidConfig(
+                    "a store cannot be combined with a base directory or project".to_owned()));
+            }
+
+            if let Some(dir) = self.dir.as_ref().filter(|dir| !dir.starts_with(store.dir())) {
+                return Err(FileBackedValueError::InvalidConfig(
+                    format!("directory {:?} is not inside the directory of the store", dir)));
+            }
+        }
+
+        if let Some(from) = self.options.migrations.latest().filter(|&from| from >= self.options.schema_version) {
+            return Err(FileBackedValueError::InvalidConfig(
+                format!("migration from schema version {} is not older than the schema version {}", from, self.options.schema_version)));
+        }
+
+        if !self.options.migrations.is_empty() && !self.format.is_self_describing() {
+            return Err(FileBackedValueError::InvalidConfig(
+                "migrations require a self-describing format, as values are migrated as JSON values".to_owned()));
+        }
+
+        let dir = match (self.dir, &self.options.store) {
+            (Some(dir), _) => dir,
+            (None, Some(store)) => store.dir().to_path_buf(),
+            (None, None) => location::default_dir(self.base_dir, self.project.as_ref())?,
+        };
+
+        Ok(FileBackedValue {
+            path: dir.join(filename),
+            format: self.format,
+            options: self.options,
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+/// Marks the contents of a backing file as a checksum frame.
+const MAGIC: &[u8; 4] = b"FBVC";
+
+/// Algorithm of the checksum that is stored in front of the serialized value, to detect corruption.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    /// A 4-byte CRC32C, which is fast and catches accidental corruption.
+    Crc32c,
+    /// A 32-byte BLAKE3 hash, which also catches deliberate tampering as long as the hash itself is trusted.
+    Blake3,
+}
+
+impl Checksum {
+    fn id(self) -> u8 {
+        match self {
+            Checksum::Crc32c => 1,
+            Checksum::Blake3 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Checksum::Crc32c),
+            2 => Some(Checksum::Blake3),
+            _ => None,
+        }
+    }
+
+    fn len(self) -> usize {
+        match self {
+            Checksum::Crc32c => 4,
+            Checksum::Blake3 => blake3::OUT_LEN,
+        }
+    }
+
+    fn compute(self, payload: &[u8]) -> Vec<u8> {
+        match self {
+            Checksum::Crc32c => crc32c::crc32c(payload).to_le_bytes().to_vec(),
+            Checksum::Blake3 => blake3::hash(payload).as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Prefix `payload` with the magic bytes, the algorithm, and the checksum of the payload.
+pub(crate) fn frame(checksum: Checksum, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + checksum.len() + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(checksum.id());
+    bytes.extend_from_slice(&checksum.compute(payload));
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Whether `bytes` start with a checksum frame.
+pub(crate) fn is_framed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Verify the checksum of a framed payload and return the payload.
+/// Bytes that are not framed, such as files written without a checksum, are returned as is.
+pub(crate) fn unframe(bytes: &[u8]) -> FileBackedValueResult<&[u8]> {
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        return Ok(bytes);
+    };
+
+    let (&id, rest) = rest.split_first().ok_or(FileBackedValueError::ChecksumMismatch)?;
+    let checksum = Checksum::from_id(id).ok_or(FileBackedValueError::ChecksumMismatch)?;
+    if rest.len() < checksum.len() {
+        return Err(FileBackedValueError::ChecksumMismatch);
+    }
+
+    let (expected, payload) = rest.split_at(checksum.len());
+    if checksum.compute(payload) != expected {
+        return Err(FileBackedValueError::ChecksumMismatch);
+    }
+
+    Ok(payload)
+}
@@ -0,0 +1,86 @@
+use std::borrow::Cow;
+#[cfg(any(feature = "gzip", feature = "lz4"))]
+use std::io::{Read, Write};
+
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const LZ4_MAGIC: &[u8] = &[0x04, 0x22, 0x4d, 0x18];
+
+/// Algorithm with which backing files are compressed, each enabled through the cargo feature of the same name.
+///
+/// Compressed files are recognized by their magic bytes, so files that were written without compression,
+/// or with another algorithm, can still be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Zstandard, which compresses well at a good speed.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Gzip, which is widely supported by other tools.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// The LZ4 frame format, which is very fast but compresses less.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// Compress `bytes` using `compression`.
+#[cfg_attr(not(any(feature = "zstd", feature = "gzip", feature = "lz4")), allow(unused_variables, reason = "no algorithm is enabled"))]
+pub(crate) fn compress(compression: Compression, bytes: &[u8]) -> FileBackedValueResult<Vec<u8>> {
+    match compression {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::encode_all(bytes, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map_err(FileBackedValueError::CompressionError),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes)
+                .and_then(|()| encoder.finish())
+                .map_err(FileBackedValueError::CompressionError)
+        }
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+            encoder.write_all(bytes)
+                .and_then(|()| encoder.finish().map_err(std::io::Error::other))
+                .map_err(FileBackedValueError::CompressionError)
+        }
+    }
+}
+
+/// Decompress `bytes` if they start with the magic bytes of a compression algorithm, and return them as is otherwise.
+pub(crate) fn decompress(bytes: &[u8]) -> FileBackedValueResult<Cow<'_, [u8]>> {
+    if bytes.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return zstd::decode_all(bytes)
+            .map(Cow::Owned)
+            .map_err(FileBackedValueError::CompressionError);
+        #[cfg(not(feature = "zstd"))]
+        return Err(FileBackedValueError::UnsupportedCompression("zstd"));
+    }
+
+    if bytes.starts_with(GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        return read_all(flate2::read::GzDecoder::new(bytes));
+        #[cfg(not(feature = "gzip"))]
+        return Err(FileBackedValueError::UnsupportedCompression("gzip"));
+    }
+
+    if bytes.starts_with(LZ4_MAGIC) {
+        #[cfg(feature = "lz4")]
+        return read_all(lz4_flex::frame::FrameDecoder::new(bytes));
+        #[cfg(not(feature = "lz4"))]
+        return Err(FileBackedValueError::UnsupportedCompression("lz4"));
+    }
+
+    Ok(Cow::Borrowed(bytes))
+}
+
+#[cfg(any(feature = "gzip", feature = "lz4"))]
+fn read_all(mut decoder: impl Read) -> FileBackedValueResult<Cow<'static, [u8]>> {
+    let mut decompressed = Vec::new();
+    decoder.read_to_end(&mut decompressed)
+        .map_err(FileBackedValueError::CompressionError)?;
+    Ok(Cow::Owned(decompressed))
+}
@@ -0,0 +1,36 @@
+use std::{ffi::OsString, path::{Path, PathBuf}, time::SystemTime};
+
+use crate::envelope::to_millis;
+
+/// What to do when a backing file exists, but its contents cannot be deserialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorruptionPolicy {
+    /// Return the error.
+    #[default]
+    Error,
+    /// Treat the value as missing, such that it is recomputed and the backing file is overwritten.
+    Recompute,
+    /// Like [`CorruptionPolicy::Recompute`], but first move the backing file aside to `<name>.corrupt-<timestamp>`,
+    /// where the timestamp is in milliseconds since the Unix epoch, such that it can be inspected later.
+    Quarantine,
+}
+
+/// Path to move the corrupt backing file at `path` to.
+/// If a file was already quarantined in the same millisecond, a counter is appended so that it is not overwritten.
+pub(crate) fn quarantine_path(path: &Path) -> PathBuf {
+    let mut filename = OsString::from(path.file_name().unwrap_or_default());
+    filename.push(format!(".corrupt-{}", to_millis(SystemTime::now())));
+    let quarantine_path = path.with_file_name(&filename);
+    if !quarantine_path.exists() {
+        return quarantine_path;
+    }
+
+    (1..)
+        .map(|counter| {
+            let mut filename = filename.clone();
+            filename.push(format!("-{}", counter));
+            path.with_file_name(filename)
+        })
+        .find(|quarantine_path| !quarantine_path.exists())
+        .expect("some counter is not in use")
+}
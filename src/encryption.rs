@@ -0,0 +1,114 @@
+#[cfg(feature = "encryption")]
+use std::{error::Error, fmt, sync::Arc};
+
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{aead::{Aead, AeadCore, KeyInit, OsRng, Payload}, XChaCha20Poly1305, XNonce};
+
+#[cfg(feature = "encryption")]
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+/// Marks the contents of a backing file as encrypted.
+///
+/// Unlike the rest of this module, this is available without the `encryption` feature,
+/// such that encrypted files are never mistaken for corrupt ones.
+const MAGIC: &[u8; 4] = b"FBVE";
+
+#[cfg(feature = "encryption")]
+/// Identifies XChaCha20-Poly1305 as the algorithm, such that others can be added later.
+const XCHACHA20_POLY1305: u8 = 1;
+
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+#[cfg(feature = "encryption")]
+/// Supplies the 256-bit key with which backing files are encrypted, for example from the keyring of the platform.
+///
+/// The key is requested whenever a value is read or written, so implementations may want to cache it.
+pub trait KeyProvider: Send + Sync {
+    fn key(&self) -> Result<[u8; 32], Box<dyn Error + Send + Sync>>;
+}
+
+#[cfg(feature = "encryption")]
+impl KeyProvider for [u8; 32] {
+    fn key(&self) -> Result<[u8; 32], Box<dyn Error + Send + Sync>> {
+        Ok(*self)
+    }
+}
+
+#[cfg(feature = "encryption")]
+/// Encrypts backing files with XChaCha20-Poly1305, using the key of a [`KeyProvider`].
+#[derive(Clone)]
+pub(crate) struct Encryption {
+    provider: Arc<dyn KeyProvider>,
+}
+
+#[cfg(feature = "encryption")]
+impl Encryption {
+    pub(crate) fn new(provider: impl KeyProvider + 'static) -> Self {
+        Self { provider: Arc::new(provider) }
+    }
+
+    /// Encrypt `plaintext` under a random nonce, prefixed with the magic bytes, the algorithm, and the nonce.
+    /// The ciphertext is bound to `filename`, such that it cannot be decrypted as the contents of another file.
+    pub(crate) fn encrypt(&self, plaintext: &[u8], filename: &[u8]) -> FileBackedValueResult<Vec<u8>> {
+        let cipher = self.cipher()?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let header = header();
+        let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad: &aad(&header, filename) })
+            .map_err(|_| FileBackedValueError::EncryptionFailed)?;
+
+        let mut bytes = Vec::with_capacity(header.len() + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        Ok(bytes)
+    }
+
+    /// Verify and decrypt `bytes`, which must have been encrypted using the same key and for the same `filename`.
+    pub(crate) fn decrypt(&self, bytes: &[u8], filename: &[u8]) -> FileBackedValueResult<Vec<u8>> {
+        let header = header();
+        let rest = bytes.strip_prefix(header.as_slice()).ok_or(FileBackedValueError::DecryptionFailed)?;
+        if rest.len() < NONCE_LEN {
+            return Err(FileBackedValueError::DecryptionFailed);
+        }
+
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        self.cipher()?
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad(&header, filename) })
+            .map_err(|_| FileBackedValueError::DecryptionFailed)
+    }
+
+    fn cipher(&self) -> FileBackedValueResult<XChaCha20Poly1305> {
+        let key = self.provider.key().map_err(FileBackedValueError::KeyUnavailable)?;
+        Ok(XChaCha20Poly1305::new(&key.into()))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl fmt::Debug for Encryption {
+    /// Omits the key provider, which might reveal the key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encryption").finish_non_exhaustive()
+    }
+}
+
+/// Whether `bytes` are the contents of an encrypted backing file.
+pub(crate) fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+#[cfg(feature = "encryption")]
+/// The magic bytes and the algorithm, which are authenticated along with the ciphertext.
+fn header() -> [u8; 5] {
+    let mut header = [0; 5];
+    header[..4].copy_from_slice(MAGIC);
+    header[4] = XCHACHA20_POLY1305;
+    header
+}
+
+#[cfg(feature = "encryption")]
+/// Data that is authenticated along with the ciphertext: the `header`, followed by the name of the backing file,
+/// such that the contents of one backing file cannot be swapped with those of another.
+fn aad(header: &[u8], filename: &[u8]) -> Vec<u8> {
+    [header, filename].concat()
+}
@@ -0,0 +1,162 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{FileBackedValueError, FileBackedValueResult, Format};
+
+/// Version of the envelope layout itself, as opposed to the schema version of the stored value.
+///
+/// Version 2 added the digest of the input files.
+const ENVELOPE_VERSION: u32 = 2;
+
+/// Metadata that is stored in the backing file alongside the value.
+///
+/// Storing these explicitly, rather than relying on the modification time of the file,
+/// means that expiry keeps working when files are copied, restored from a backup, or touched by other tools.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Header {
+    /// When the value was written.
+    pub(crate) created: SystemTime,
+    /// When the value expires, if an expiry was known at the time it was written.
+    pub(crate) expires: Option<SystemTime>,
+    /// Schema version of the stored value.
+    pub(crate) schema: u32,
+    /// Digest of the input files the value was derived from, if any were registered.
+    pub(crate) inputs: Option<String>,
+}
+
+/// A value read from a backing file, along with its metadata.
+#[derive(Clone, Debug)]
+pub(crate) struct Stored<T> {
+    pub(crate) header: Header,
+    pub(crate) value: T,
+}
+
+/// The serialized form of a value with its metadata. Times are stored in milliseconds since the Unix epoch.
+///
+/// Fields are never skipped, as formats that are not self-describing cannot deserialize skipped fields.
+/// For the same reason, new fields are added at the end.
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T: ?Sized> {
+    version: u32,
+    schema: u32,
+    created: u64,
+    expires: Option<u64>,
+    value: &'a T,
+    inputs: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    schema: u32,
+    created: u64,
+    expires: Option<u64>,
+    value: T,
+    inputs: Option<String>,
+}
+
+/// The leading fields of every envelope version, which all formats can read regardless of the type of the value.
+#[derive(Deserialize)]
+struct EnvelopePrefix {
+    version: u32,
+    schema: u32,
+}
+
+/// Layout of version 1 envelopes, which formats that are not self-describing cannot read as an [`Envelope`].
+#[derive(Deserialize)]
+struct EnvelopeV1<T> {
+    version: u32,
+    schema: u32,
+    created: u64,
+    expires: Option<u64>,
+    value: T,
+}
+
+impl<T> From<EnvelopeV1<T>> for Envelope<T> {
+    fn from(envelope: EnvelopeV1<T>) -> Self {
+        Self {
+            version: envelope.version,
+            schema: envelope.schema,
+            created: envelope.created,
+            expires: envelope.expires,
+            value: envelope.value,
+            inputs: None,
+        }
+    }
+}
+
+/// Serialize `value` together with its `header`.
+pub(crate) fn encode<T, Fmt>(format: &Fmt, header: &Header, value: &T) -> FileBackedValueResult<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    format.serialize(&EnvelopeRef {
+        version: ENVELOPE_VERSION,
+        schema: header.schema,
+        created: to_millis(header.created),
+        expires: header.expires.map(to_millis),
+        value,
+        inputs: header.inputs.as_deref(),
+    })
+}
+
+/// Deserialize a value together with its header.
+///
+/// Files written before values were wrapped in an envelope contain just the value, and were always JSON.
+/// For formats that can read these files, the header is reconstructed from `modified`, the modification time of the file.
+pub(crate) fn decode<T, Fmt>(format: &Fmt, bytes: &[u8], modified: Option<SystemTime>) -> FileBackedValueResult<Stored<T>>
+where
+    T: DeserializeOwned,
+    Fmt: Format,
+{
+    let envelope = format.deserialize::<Envelope<T>>(bytes)
+        .or_else(|e| format.deserialize::<EnvelopeV1<T>>(bytes).map(Envelope::from).map_err(|_| e));
+    match envelope {
+        Ok(envelope) if (1..=ENVELOPE_VERSION).contains(&envelope.version) => Ok(Stored {
+            header: Header {
+                created: from_millis(envelope.created),
+                expires: envelope.expires.map(from_millis),
+                schema: envelope.schema,
+                inputs: envelope.inputs,
+            },
+            value: envelope.value,
+        }),
+        Ok(envelope) => Err(FileBackedValueError::UnsupportedEnvelope(envelope.version)),
+        // If the file does not contain a bare value either, report why it is not a valid envelope.
+        Err(e) if format.reads_legacy_files() => format.deserialize::<T>(bytes)
+            .map(|value| Stored {
+                header: Header {
+                    // Without a modification time, the age is unknown; assume the value is as old as possible.
+                    created: modified.unwrap_or(UNIX_EPOCH),
+                    expires: None,
+                    schema: 0,
+                    inputs: None,
+                },
+                value,
+            })
+            .map_err(|_| e),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read just the schema version of a serialized envelope, without deserializing the value.
+/// Returns None if `bytes` do not start with an envelope of a supported version.
+pub(crate) fn decode_schema<Fmt>(format: &Fmt, bytes: &[u8]) -> Option<u32>
+where
+    Fmt: Format,
+{
+    format.deserialize::<EnvelopePrefix>(bytes).ok()
+        .filter(|prefix| (1..=ENVELOPE_VERSION).contains(&prefix.version))
+        .map(|prefix| prefix.schema)
+}
+
+pub(crate) fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis().try_into().unwrap_or(u64::MAX))
+}
+
+fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
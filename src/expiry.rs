@@ -0,0 +1,201 @@
+use std::{fmt::Debug, fs, path::{Path, PathBuf}, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use chrono::{DateTime, Local, NaiveTime};
+
+/// Decides when a value expires, and thus needs to be recomputed.
+///
+/// Policies are evaluated whenever a value is read, so they can depend on state that changes after the value was written,
+/// such as the modification times of the files it was derived from.
+pub trait ExpiryPolicy: Debug + Send + Sync {
+    /// When a value that was written at `created` expires, or None if it never does.
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime>;
+
+    /// Check the configuration of the policy when a value is built, and describe why it is invalid if it is.
+    /// Policies that cannot be misconfigured can rely on the default, which accepts everything.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Values expire a fixed duration after they were written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ttl(pub Duration);
+
+impl ExpiryPolicy for Ttl {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        created.checked_add(self.0)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.0.is_zero() {
+            return Err("TTL must be greater than zero".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Values never expire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Never;
+
+impl ExpiryPolicy for Never {
+    fn expires_at(&self, _created: SystemTime) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// Values expire at the next occurrence of a time of day, in the local time zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DailyAt {
+    time: NaiveTime,
+}
+
+impl DailyAt {
+    /// Expire at `hour:minute` local time, or None if that is not a valid time of day.
+    pub fn new(hour: u32, minute: u32) -> Option<Self> {
+        NaiveTime::from_hms_opt(hour, minute, 0).map(|time| Self { time })
+    }
+
+    /// Expire at the next local midnight.
+    pub fn midnight() -> Self {
+        Self { time: NaiveTime::MIN }
+    }
+}
+
+impl ExpiryPolicy for DailyAt {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        let created = DateTime::<Local>::from(created);
+        // Daylight saving time can skip the time of day entirely, in which case the next day is tried.
+        created.date_naive()
+            .iter_days()
+            .take(3)
+            .filter_map(|date| date.and_time(self.time).and_local_timezone(Local).earliest())
+            .find(|expires| *expires > created)
+            .map(SystemTime::from)
+    }
+}
+
+/// Values expire at the next multiple of a period since the Unix epoch.
+/// For example, with a period of one hour values expire at the start of the next hour in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Every(pub Duration);
+
+impl ExpiryPolicy for Every {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        let period = self.0.as_millis();
+        if period == 0 {
+            return Some(created);
+        }
+
+        let since_epoch = created.duration_since(UNIX_EPOCH).ok()?.as_millis();
+        let next = (since_epoch / period + 1) * period;
+        UNIX_EPOCH.checked_add(Duration::from_millis(next.try_into().ok()?))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        // Periods are counted in milliseconds, so a shorter period would be zero as well.
+        if self.0.as_millis() == 0 {
+            return Err("period must be at least one millisecond".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Values expire when any of the given source files is modified after the value was written.
+/// Source files that are missing or whose modification time cannot be read are ignored,
+/// such that a missing source does not make every value dirty as soon as it is written.
+///
+/// This is meant for values derived from files, such as build artifacts.
+/// Sources that are modified while the value is being computed are not noticed, as the value is written afterwards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourcesChanged {
+    paths: Vec<PathBuf>,
+}
+
+impl SourcesChanged {
+    pub fn new<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            paths: paths.into_iter().map(|path| path.as_ref().to_path_buf()).collect(),
+        }
+    }
+}
+
+impl ExpiryPolicy for SourcesChanged {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        self.paths.iter()
+            .filter_map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+            .filter(|modified| *modified >= created)
+            .min()
+    }
+}
+
+/// Values expire as soon as any of the given policies expires them.
+#[derive(Clone, Debug, Default)]
+pub struct AnyOf {
+    policies: Vec<Arc<dyn ExpiryPolicy>>,
+}
+
+impl AnyOf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also expire values when `policy` does.
+    pub fn with(mut self, policy: impl ExpiryPolicy + 'static) -> Self {
+        self.policies.push(Arc::new(policy));
+        self
+    }
+}
+
+impl ExpiryPolicy for AnyOf {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        self.policies.iter()
+            .fold(None, |expires, policy| earliest(expires, policy.expires_at(created)))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.policies.iter().try_for_each(|policy| policy.validate())
+    }
+}
+
+/// Values expire only once all of the given policies expire them.
+/// Without any policies, values never expire.
+#[derive(Clone, Debug, Default)]
+pub struct AllOf {
+    policies: Vec<Arc<dyn ExpiryPolicy>>,
+}
+
+impl AllOf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only expire values once `policy` does as well.
+    pub fn with(mut self, policy: impl ExpiryPolicy + 'static) -> Self {
+        self.policies.push(Arc::new(policy));
+        self
+    }
+}
+
+impl ExpiryPolicy for AllOf {
+    fn expires_at(&self, created: SystemTime) -> Option<SystemTime> {
+        // If any policy never expires the value, neither does the combination.
+        self.policies.iter()
+            .map(|policy| policy.expires_at(created))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.policies.iter().try_for_each(|policy| policy.validate())
+    }
+}
+
+/// The earliest of two optional expiry times, where None means never.
+pub(crate) fn earliest(a: Option<SystemTime>, b: Option<SystemTime>) -> Option<SystemTime> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}, sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError, Weak}};
+
+/// Per-path mutexes of the backing files that are currently being computed in this process.
+static FLIGHTS: LazyLock<Mutex<HashMap<PathBuf, Weak<Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// Coordinates the threads of this process that want to compute the value of the same backing file,
+/// such that only one of them does the computation while the others wait for its result.
+#[derive(Debug)]
+pub(crate) struct Flight {
+    mutex: Arc<Mutex<()>>,
+}
+
+impl Flight {
+    /// The flight of the backing file at `path`, shared with all other threads currently using that path.
+    pub(crate) fn of(path: &Path) -> Self {
+        let mut flights = FLIGHTS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mutex) = flights.get(path).and_then(Weak::upgrade) {
+            return Self { mutex };
+        }
+
+        // Forget about the flights that have since finished.
+        flights.retain(|_, mutex| mutex.strong_count() > 0);
+
+        let mutex = Arc::new(Mutex::new(()));
+        flights.insert(path.to_path_buf(), Arc::downgrade(&mutex));
+        Self { mutex }
+    }
+
+    /// Block until no other thread of this process is computing the value.
+    ///
+    /// A computation that panicked does not poison the flight; the next thread simply tries again.
+    pub(crate) fn join(&self) -> MutexGuard<'_, ()> {
+        self.mutex.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Backing files of which a background refresh is currently running in this process.
+static REFRESHES: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+
+/// Marks a background refresh of a backing file as running, until it is dropped.
+#[derive(Debug)]
+pub(crate) struct Refresh {
+    path: PathBuf,
+}
+
+impl Refresh {
+    /// Start a background refresh of the backing file at `path`, or return None if one is already running.
+    pub(crate) fn start(path: &Path) -> Option<Self> {
+        let mut refreshes = REFRESHES.lock().unwrap_or_else(PoisonError::into_inner);
+        refreshes.insert(path.to_path_buf())
+            .then(|| Self { path: path.to_path_buf() })
+    }
+}
+
+impl Drop for Refresh {
+    fn drop(&mut self) {
+        // This also runs when the refresh panicked, such that the next access can try again.
+        REFRESHES.lock().unwrap_or_else(PoisonError::into_inner).remove(&self.path);
+    }
+}
+
+/// Per-path mutexes of the backing files that are currently being computed by async tasks in this process.
+#[cfg(feature = "async")]
+static ASYNC_FLIGHTS: LazyLock<Mutex<HashMap<PathBuf, Weak<tokio::sync::Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// Like [`Flight`], but for async tasks, which must not block their executor while waiting.
+///
+/// The registry is separate from that of [`Flight`], as threads cannot wait on a tokio mutex from within a runtime,
+/// so a thread and a task computing the same value are not coalesced.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub(crate) struct AsyncFlight {
+    mutex: Arc<tokio::sync::Mutex<()>>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncFlight {
+    /// The flight of the backing file at `path`, shared with all other tasks currently using that path.
+    pub(crate) fn of(path: &Path) -> Self {
+        let mut flights = ASYNC_FLIGHTS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mutex) = flights.get(path).and_then(Weak::upgrade) {
+            return Self { mutex };
+        }
+
+        // Forget about the flights that have since finished.
+        flights.retain(|_, mutex| mutex.strong_count() > 0);
+
+        let mutex = Arc::new(tokio::sync::Mutex::new(()));
+        flights.insert(path.to_path_buf(), Arc::downgrade(&mutex));
+        Self { mutex }
+    }
+
+    /// Wait until no other task of this process is computing the value.
+    pub(crate) async fn join(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.mutex.lock().await
+    }
+}
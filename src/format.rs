@@ -0,0 +1,226 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+/// A serialization format in which values are stored in their backing file.
+///
+/// JSON is always available; the other built-in formats are enabled through cargo features.
+pub trait Format {
+    /// Serialize `value` into the bytes that are written to the backing file.
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized;
+
+    /// Deserialize a value from the bytes read from the backing file.
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned;
+
+    /// Whether this format can read the files of older versions of this crate, which stored bare JSON values
+    /// without any metadata.
+    fn reads_legacy_files(&self) -> bool {
+        false
+    }
+
+    /// Whether values can be deserialized without knowing their type, as is needed to migrate them.
+    fn is_self_describing(&self) -> bool {
+        true
+    }
+}
+
+/// Compact JSON, the default format.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        serde_json::to_vec(value).map_err(FileBackedValueError::JsonError)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        serde_json::from_slice(bytes).map_err(FileBackedValueError::JsonError)
+    }
+
+    fn reads_legacy_files(&self) -> bool {
+        true
+    }
+}
+
+/// Human-readable, indented JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrettyJson;
+
+impl Format for PrettyJson {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        serde_json::to_vec_pretty(value).map_err(FileBackedValueError::JsonError)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        serde_json::from_slice(bytes).map_err(FileBackedValueError::JsonError)
+    }
+
+    fn reads_legacy_files(&self) -> bool {
+        true
+    }
+}
+
+/// Bincode, using its standard configuration.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Format for Bincode {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        bincode::serde::encode_to_vec(value, bincode::config::standard())
+            .map_err(FileBackedValueError::format)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(value, _)| value)
+            .map_err(FileBackedValueError::format)
+    }
+
+    fn is_self_describing(&self) -> bool {
+        false
+    }
+}
+
+/// Postcard, a compact binary format.
+#[cfg(feature = "postcard")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Format for Postcard {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        postcard::to_allocvec(value).map_err(FileBackedValueError::format)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        postcard::from_bytes(bytes).map_err(FileBackedValueError::format)
+    }
+
+    fn is_self_describing(&self) -> bool {
+        false
+    }
+}
+
+/// CBOR, the Concise Binary Object Representation.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Format for Cbor {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(FileBackedValueError::format)?;
+        Ok(bytes)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        ciborium::from_reader(bytes).map_err(FileBackedValueError::format)
+    }
+}
+
+/// MessagePack, with structs stored as maps so that fields can be added later.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Format for MessagePack {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        rmp_serde::to_vec_named(value).map_err(FileBackedValueError::format)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        rmp_serde::from_slice(bytes).map_err(FileBackedValueError::format)
+    }
+}
+
+/// TOML. Note that only tables can be stored at the top level of a TOML document.
+#[cfg(feature = "toml")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl Format for Toml {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        toml::to_string(value)
+            .map(String::into_bytes)
+            .map_err(FileBackedValueError::format)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        toml::from_slice(bytes).map_err(FileBackedValueError::format)
+    }
+}
+
+/// RON, the Rusty Object Notation.
+#[cfg(feature = "ron")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ron;
+
+#[cfg(feature = "ron")]
+impl Format for Ron {
+    fn serialize<T>(&self, value: &T) -> FileBackedValueResult<Vec<u8>>
+    where
+        T: Serialize + ?Sized
+    {
+        ron::to_string(value)
+            .map(String::into_bytes)
+            .map_err(FileBackedValueError::format)
+    }
+
+    fn deserialize<T>(&self, bytes: &[u8]) -> FileBackedValueResult<T>
+    where
+        T: DeserializeOwned
+    {
+        ron::de::from_bytes(bytes).map_err(FileBackedValueError::format)
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fs, io, path::PathBuf, sync::{Arc, Mutex, PoisonError}, time::{Duration, SystemTime}};
+
+/// Coarsest granularity of modification times among common file systems, which is two seconds on FAT.
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// The files that a value is derived from, along with the digest of their contents that was computed last.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Inputs {
+    paths: Vec<PathBuf>,
+    /// Shared between clones, which have the same paths.
+    last: Arc<Mutex<Option<Snapshot>>>,
+}
+
+/// A digest of the input files, along with the size and modification time of each file when it was computed.
+#[derive(Debug)]
+struct Snapshot {
+    stamps: Vec<Option<Stamp>>,
+    digest: String,
+    /// When the stamps were taken, before the files were hashed.
+    taken: SystemTime,
+}
+
+impl Snapshot {
+    /// Whether the digest still holds for files with the given stamps.
+    ///
+    /// Like git's racy-clean check, a file that was modified within the granularity of modification times before
+    /// the snapshot was taken might be modified again without its modification time changing, so it is never trusted.
+    fn matches(&self, stamps: &[Option<Stamp>]) -> bool {
+        self.stamps == stamps && stamps.iter().flatten().all(|stamp| stamp.modified
+            .is_some_and(|modified| self.taken.duration_since(modified).is_ok_and(|age| age >= MTIME_GRANULARITY)))
+    }
+}
+
+/// Cheap fingerprint of an input file, or None in a snapshot if the file does not exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Inputs {
+    pub(crate) fn new(paths: Vec<PathBuf>) -> Self {
+        Self { paths, last: Arc::default() }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Digest of the contents of the input files, which changes whenever any of them does.
+    ///
+    /// The files are only hashed again when the size or modification time of any of them has changed
+    /// since the last digest, so repeated reads do not need to hash large inputs every time.
+    /// Files without a modification time, or that were modified shortly before the last digest, are always hashed.
+    pub(crate) fn digest(&self) -> io::Result<String> {
+        let taken = SystemTime::now();
+        let stamps = self.paths.iter()
+            .map(|path| match fs::metadata(path) {
+                Ok(metadata) => Ok(Some(Stamp { modified: metadata.modified().ok(), len: metadata.len() })),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(snapshot) = last.as_ref().filter(|snapshot| snapshot.matches(&stamps)) {
+            return Ok(snapshot.digest.clone());
+        }
+
+        let digest = digest(&self.paths)?;
+        *last = Some(Snapshot { stamps, digest: digest.clone(), taken });
+        Ok(digest)
+    }
+}
+
+/// Digest of the contents of the input files at `paths`, which changes whenever any of them does.
+///
+/// Missing files are part of the digest, such that creating or deleting an input changes it as well.
+fn digest(paths: &[PathBuf]) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    for path in paths {
+        let path_bytes = path.as_os_str().as_encoded_bytes();
+        hasher.update(&(path_bytes.len() as u64).to_le_bytes());
+        hasher.update(path_bytes);
+
+        match fs::File::open(path) {
+            Ok(mut file) => {
+                // Hash the contents separately, such that they cannot run into the path of the next input.
+                let contents = blake3::Hasher::new().update_reader(&mut file)?.finalize();
+                hasher.update(&[1]);
+                hasher.update(contents.as_bytes());
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                hasher.update(&[0]);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest_changes_with_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        let inputs = Inputs::new(vec![path.clone()]);
+
+        let missing = inputs.digest().unwrap();
+        fs::write(&path, "a").unwrap();
+        let created = inputs.digest().unwrap();
+        fs::write(&path, "bb").unwrap();
+        let modified = inputs.digest().unwrap();
+
+        assert_ne!(missing, created);
+        assert_ne!(created, modified);
+        assert_eq!(modified, inputs.digest().unwrap());
+    }
+
+    #[test]
+    fn unchanged_stamps_reuse_the_last_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        fs::write(&path, "a").unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        let inputs = Inputs::new(vec![path.clone()]);
+        let digest = inputs.digest().unwrap();
+
+        // Change the contents behind its back, but restore the size and modification time.
+        fs::write(&path, "b").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert_eq!(inputs.digest().unwrap(), digest);
+
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified + Duration::from_secs(1)).unwrap();
+        assert_ne!(inputs.digest().unwrap(), digest);
+    }
+
+    #[test]
+    fn recently_modified_files_are_hashed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        fs::write(&path, "a").unwrap();
+        let inputs = Inputs::new(vec![path.clone()]);
+        let digest = inputs.digest().unwrap();
+
+        // A rewrite with the same size within the same tick of a coarse modification time.
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "b").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert_ne!(inputs.digest().unwrap(), digest);
+    }
+}
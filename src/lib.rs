@@ -1,36 +1,189 @@
-use std::{fs, io::{self, BufReader, BufWriter}, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+use std::{borrow::Cow, convert::Infallible, error::Error, ffi::OsString, fmt, fs, io::{self, Read, Write}, path::{Path, PathBuf}, process, sync::{atomic::{AtomicU64, Ordering}, Arc}, thread, time::{Duration, SystemTime}};
 
 use serde::{de::DeserializeOwned, Serialize};
 
+#[cfg(feature = "async")]
+mod async_io;
+mod builder;
+mod checksum;
+mod compression;
+mod corruption;
+mod encryption;
+mod envelope;
+mod expiry;
+mod flight;
+mod format;
+mod inputs;
+mod location;
+mod lock;
+mod map;
+#[cfg(feature = "macros")]
+mod memoize;
+mod migration;
+mod store;
+mod typed;
+
+pub use builder::FileBackedValueBuilder;
+pub use checksum::Checksum;
+pub use compression::Compression;
+pub use corruption::CorruptionPolicy;
+#[cfg(feature = "encryption")]
+pub use encryption::KeyProvider;
+pub use expiry::{AllOf, AnyOf, DailyAt, Every, ExpiryPolicy, Never, SourcesChanged, Ttl};
+pub use format::*;
+pub use location::{BaseDir, Project, DIR_ENV_VAR};
+pub use lock::LockPolicy;
+pub use map::FileBackedMap;
+pub use store::{EvictionPolicy, FileBackedStore, StoreUsage};
+#[cfg(feature = "macros")]
+pub use file_backed_value_macros::file_backed;
+pub use typed::TypedFileBackedValue;
+
+/// Items used by the code generated by [`file_backed`], which are not part of the public API.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::memoize::{key, memoize};
+}
+
+/// Compiles the examples in the README, which use the `macros` feature.
+#[cfg(all(doctest, feature = "macros"))]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
+
+#[cfg(feature = "encryption")]
+use encryption::Encryption;
+use envelope::{Header, Stored};
+use flight::{Flight, Refresh};
+use inputs::Inputs;
+use lock::FileLock;
+use migration::Migrations;
+
+/// A lazily computed value which is persisted in a backing file.
+///
+/// All accessors take `&self`, so a single value can be shared between threads, for example through an `Arc`.
+/// Threads that concurrently compute the same missing value through `get_or_insert_with` are coalesced,
+/// such that one of them computes the value while the others wait for its result.
 #[derive(Clone, Debug)]
-pub struct FileBackedValue {
+pub struct FileBackedValue<Fmt = Json> {
     path: PathBuf,
+    format: Fmt,
+    options: Options,
+}
+
+/// Format-independent configuration of a file-backed value.
+#[derive(Clone, Debug, Default)]
+struct Options {
     dirty_time: Option<Duration>,
+    expiry: Option<Arc<dyn ExpiryPolicy>>,
+    inputs: Inputs,
+    lock_policy: LockPolicy,
+    corruption_policy: CorruptionPolicy,
+    checksum: Option<Checksum>,
+    compression: Option<Compression>,
+    #[cfg(feature = "encryption")]
+    encryption: Option<Encryption>,
+    stale_if_error: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    schema_version: u32,
+    migrations: Migrations,
+    store: Option<FileBackedStore>,
 }
 
 #[derive(Debug)]
 pub enum FileBackedValueError {
     FileError(io::Error),
     JsonError(serde_json::Error),
+    /// Serialization error of a non-JSON format.
+    FormatError(Box<dyn Error + Send + Sync>),
+    /// The configuration passed to a builder is invalid.
+    InvalidConfig(String),
+    /// No valid home directory was found, and [`DIR_ENV_VAR`] is not set.
+    NoHomeDirectory,
+    /// The requested base directory does not exist on this platform, and [`DIR_ENV_VAR`] is not set.
+    NoBaseDirectory(BaseDir),
+    /// The backing file was written by a newer version of this crate, using an unknown envelope version.
+    UnsupportedEnvelope(u32),
+    /// The stored value has an older schema version, but no migration is registered from the given version.
+    MissingMigration(u32),
+    /// The stored value has a newer schema version than the current one, so it is neither read nor overwritten.
+    NewerSchema(u32),
+    /// The checksum of the backing file does not match its contents, or the checksum itself is damaged.
+    ChecksumMismatch,
+    /// The backing file could not be compressed or decompressed.
+    CompressionError(io::Error),
+    /// The backing file is compressed with the given algorithm, but its cargo feature is not enabled.
+    UnsupportedCompression(&'static str),
+    /// The backing file could not be authenticated and decrypted, because it was encrypted with another key
+    /// or for another file, it was tampered with, or it is encrypted while no key was configured.
+    /// Unlike other invalid contents, this is not handled by the [`CorruptionPolicy`], as the file is likely intact.
+    DecryptionFailed,
+    /// The value could not be encrypted.
+    EncryptionFailed,
+    /// The key provider could not supply the encryption key.
+    KeyUnavailable(Box<dyn Error + Send + Sync>),
 }
 
 pub type FileBackedValueResult<T> = Result<T, FileBackedValueError>;
 
+/// A value which might have been served after it expired, because it could not be recomputed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaybeStale<T> {
+    /// The value has not expired, or was just recomputed.
+    Fresh(T),
+    /// The value has expired, and recomputing it failed or is still in progress.
+    Stale(T),
+}
+
+/// Error of a computation that may fail, such as in [`FileBackedValue::get_or_try_insert_with`].
+#[derive(Debug)]
+pub enum TryInsertError<E> {
+    /// The computation itself failed; nothing was written.
+    Compute(E),
+    /// Reading or writing the backing file failed.
+    Storage(FileBackedValueError),
+}
+
 impl FileBackedValue
 {
+    /// Start building a file-backed value with the given filename.
+    ///
+    /// Unless configured otherwise, the value is stored as JSON in the user's data directory.
+    pub fn builder(filename: &str) -> FileBackedValueBuilder {
+        FileBackedValueBuilder::new(filename)
+    }
+
     /// Create a file-backed value in the user's data directory.
     ///
     /// ### Example
     /// - Linux: `/home/alice/.local/share`
     /// - MacOS: `/Users/Alice/Library/Application Support`
     /// - Windows: `C:\Users\Alice\AppData\Roaming`
+    ///
+    /// If the [`DIR_ENV_VAR`] environment variable is set, its `data` subdirectory is used instead.
+    ///
+    /// ### Panics
+    /// If no valid home directory is found. See [`FileBackedValue::try_new`] for a non-panicking version.
     pub fn new(filename: &str) -> Self {
-        let parent = PathBuf::from(directories::BaseDirs::new().expect("No valid home directory found").data_dir());
-        let filename = sanitize_filename::sanitize(filename);
-        Self {
-            path: parent.join(filename),
-            dirty_time: None,
-        }
+        Self::try_new(filename).expect("No valid home directory found")
+    }
+
+    /// Create a file-backed value in the user's data directory,
+    /// or return an error if no valid home directory is found.
+    pub fn try_new(filename: &str) -> FileBackedValueResult<Self> {
+        Self::new_in(filename, BaseDir::Data)
+    }
+
+    /// Create a file-backed value in the given kind of standard directory.
+    pub fn new_in(filename: &str, base_dir: BaseDir) -> FileBackedValueResult<Self> {
+        let parent = location::default_dir(base_dir, None)?;
+        Ok(Self::new_at(filename, &parent))
+    }
+
+    /// Create a file-backed value in the given kind of standard directory, in a subdirectory specific to `project`.
+    pub fn new_in_project(filename: &str, base_dir: BaseDir, project: &Project) -> FileBackedValueResult<Self> {
+        let parent = location::default_dir(base_dir, Some(project))?;
+        Ok(Self::new_at(filename, &parent))
     }
 
     /// Create a file-backed value in the specified directory.
@@ -39,13 +192,109 @@ impl FileBackedValue
         let filename = sanitize_filename::sanitize(filename);
         Self {
             path: parent.join(filename),
-            dirty_time: None,
+            format: Json,
+            options: Options::default(),
+        }
+    }
+}
+
+impl<Fmt> FileBackedValue<Fmt>
+where
+    Fmt: Format
+{
+    /// Store the value using `format` instead of the current format.
+    ///
+    /// Note that values which were already written in the previous format can no longer be read.
+    pub fn with_format<NewFmt: Format>(self, format: NewFmt) -> FileBackedValue<NewFmt> {
+        FileBackedValue {
+            path: self.path,
+            format,
+            options: self.options,
         }
     }
 
     /// Set the duration after which the file is considered dirty and needs to be recomputed.
     pub fn set_dirty_time(&mut self, dirty_time: Duration) {
-        self.dirty_time = Some(dirty_time);
+        self.options.dirty_time = Some(dirty_time);
+    }
+
+    /// Set the policy that decides when the file is considered dirty and needs to be recomputed.
+    /// If a dirty time is set as well, values expire at whichever comes first.
+    pub fn set_expiry(&mut self, policy: impl ExpiryPolicy + 'static) {
+        self.options.expiry = Some(Arc::new(policy));
+    }
+
+    /// Register the files that the value is derived from.
+    /// A digest of their contents is stored alongside the value, and the value is dirty whenever they change.
+    ///
+    /// Inputs are hashed when the value is written, so changes made while the value is being computed are not noticed.
+    /// They are only hashed again when the size or modification time of any of them changes,
+    /// or when any of them was modified so shortly before it was last hashed that its modification time cannot be trusted.
+    pub fn set_inputs<P: AsRef<Path>>(&mut self, paths: impl IntoIterator<Item = P>) {
+        self.options.inputs = Inputs::new(paths.into_iter().map(|path| path.as_ref().to_path_buf()).collect());
+    }
+
+    /// Set when to take advisory locks on the backing file, to coordinate access between processes.
+    pub fn set_lock_policy(&mut self, lock_policy: LockPolicy) {
+        self.options.lock_policy = lock_policy;
+    }
+
+    /// Store a checksum of the serialized value, which is verified when it is read.
+    /// Files that were written without a checksum are then dirty, such that they are recomputed with one.
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.options.checksum = Some(checksum);
+    }
+
+    /// Compress the backing file using `compression`.
+    /// Files that were written without compression, or using another enabled algorithm, can still be read.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.options.compression = Some(compression);
+    }
+
+    /// Encrypt the backing file with XChaCha20-Poly1305, using the key supplied by `key_provider`.
+    /// Files that were written with another key or under another name can no longer be read, and are reported as
+    /// [`FileBackedValueError::DecryptionFailed`] regardless of the corruption policy.
+    /// Files that were written without encryption are dirty, such that they are recomputed and overwritten encrypted.
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption(&mut self, key_provider: impl KeyProvider + 'static) {
+        self.options.encryption = Some(Encryption::new(key_provider));
+    }
+
+    /// Set what to do when the backing file cannot be deserialized.
+    pub fn set_corruption_policy(&mut self, corruption_policy: CorruptionPolicy) {
+        self.options.corruption_policy = corruption_policy;
+    }
+
+    /// Keep expired values around, such that `get_or_try_refresh_with` can still return them if recomputing fails,
+    /// as long as they expired at most `max_staleness` ago.
+    pub fn set_stale_if_error(&mut self, max_staleness: Duration) {
+        self.options.stale_if_error = Some(max_staleness);
+    }
+
+    /// Let `get_or_revalidate_with` return expired values immediately while they are recomputed in the background,
+    /// as long as they expired at most `max_staleness` ago.
+    pub fn set_stale_while_revalidate(&mut self, max_staleness: Duration) {
+        self.options.stale_while_revalidate = Some(max_staleness);
+    }
+
+    /// Set the schema version that is stored alongside new values.
+    /// Stored values with an older schema version are considered dirty, unless they can be migrated.
+    /// Reading a stored value with a newer schema version results in [`FileBackedValueError::NewerSchema`],
+    /// rather than overwriting a value written by a newer version of the program.
+    pub fn set_schema_version(&mut self, schema_version: u32) {
+        self.options.schema_version = schema_version;
+    }
+
+    /// Register `migrate`, which upgrades stored values of schema version `from` to version `from + 1`.
+    ///
+    /// Once any migration is registered, values of older schema versions are migrated step by step when they are read,
+    /// instead of being considered dirty. Reading a value for which a step is missing then results in an error.
+    /// The backing file is rewritten with the result while holding the exclusive lock, provided that it has not
+    /// changed since it was read; without locking, the value is migrated on every read until it is written again.
+    /// Values are migrated as [`serde_json::Value`]s, which requires a self-describing format;
+    /// [`FileBackedValueBuilder::build`] rejects migrations for formats that are not.
+    pub fn add_migration(&mut self, from: u32, migrate: impl Fn(serde_json::Value) -> serde_json::Value + Send + Sync + 'static) {
+        self.options.migrations.insert(from, migrate);
     }
 
     /// Path to the backing file.
@@ -54,136 +303,788 @@ impl FileBackedValue
     }
 
     /// Clear the currently stored value and remove the backing file.
-    pub fn clear(&mut self) -> io::Result<()> {
+    pub fn clear(&self) -> io::Result<()> {
+        let _lock = self.lock(FileLock::exclusive)?;
         fs::remove_file(self.path())
     }
 
     /// Get the current value, which might be None if the backing file does not yet exist.
-    pub fn get<T>(&mut self) -> FileBackedValueResult<Option<T>>
+    pub fn get<T>(&self) -> FileBackedValueResult<Option<T>>
     where
         T: DeserializeOwned
     {
-        if self.file_is_dirty() {
-            Ok(None)
-        } else {
-            read_file(&self.path)
-        }
+        self.get_stored().map(|stored| stored.map(|stored| stored.value))
     }
 
     /// Get the current value, or insert `default` if the backing file does not exist or is dirty.
-    pub fn get_or_insert<T>(&mut self, default: T) -> FileBackedValueResult<T>
+    pub fn get_or_insert<T>(&self, default: T) -> FileBackedValueResult<T>
     where
         T: DeserializeOwned + Serialize
     {
-        if self.file_is_dirty() {
-            self.insert(&default);
-            Ok(default)
-        } else {
-            // The file has not been read before; read it now and store the value.
-            // The file must exists because otherwise it will have been marked as dirty.
-            let res: Option<T> = read_file(&self.path)?;
-            Ok(res.unwrap())
-        }
+        self.get_or_insert_with(|| default)
     }
 
     /// Get the current value, or insert `default()` if the backing file does not exist or is dirty.
-    pub fn get_or_insert_with<F, T>(&mut self, default: F) -> FileBackedValueResult<T>
+    pub fn get_or_insert_with<F, T>(&self, default: F) -> FileBackedValueResult<T>
     where
         F: FnOnce() -> T,
         T: DeserializeOwned + Serialize,
     {
-        if self.file_is_dirty() {
-            let res = default();
-            self.insert(&res);
-            Ok(res)
-        } else {
-            // The file has not been read before; read it now and store the value.
-            // The file must exists because otherwise it will have been marked as dirty.
-            let res: Option<T> = read_file(&self.path)?;
-            Ok(res.unwrap())
+        self.get_or_try_insert_with(|| Ok::<_, Infallible>(default()))
+            .map_err(TryInsertError::into_storage)
+    }
+
+    /// Get the current value, or insert the result of `default()` if the backing file does not exist or is dirty.
+    /// If `default()` returns an error, nothing is written and the error is returned.
+    pub fn get_or_try_insert_with<F, T, E>(&self, default: F) -> Result<T, TryInsertError<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+        T: DeserializeOwned + Serialize,
+    {
+        self.get_or_try_insert_stored_with(default)
+            .map(|stored| stored.value)
+    }
+
+    /// Like [`FileBackedValue::get_or_try_insert_with`], but if `default()` fails while an expired value exists,
+    /// that value is returned as [`MaybeStale::Stale`] instead, provided that stale-if-error is enabled
+    /// and the value has not been expired for longer than the configured maximum staleness.
+    pub fn get_or_try_refresh_with<F, T, E>(&self, default: F) -> Result<MaybeStale<T>, TryInsertError<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+        T: DeserializeOwned + Serialize,
+    {
+        match self.get_or_try_insert_with(default) {
+            Ok(res) => Ok(MaybeStale::Fresh(res)),
+            Err(TryInsertError::Compute(e)) => match self.get_stale(self.options.stale_if_error)? {
+                Some(res) => Ok(MaybeStale::Stale(res)),
+                None => Err(TryInsertError::Compute(e)),
+            },
+            Err(e) => Err(e),
         }
     }
 
-    /// Inserts `value` into the option and writes it to the backing file.
-    pub fn insert<T>(&mut self, value: &T)
+    /// Like [`FileBackedValue::get_or_insert_with`], but if an expired value exists, that value is returned
+    /// immediately as [`MaybeStale::Stale`] while `default()` recomputes it on a background thread,
+    /// provided that stale-while-revalidate is enabled and the value has not been expired for longer than
+    /// the configured maximum staleness.
+    ///
+    /// Errors that occur while writing the recomputed value in the background are ignored;
+    /// the next access will simply try again.
+    pub fn get_or_revalidate_with<F, T>(&self, default: F) -> FileBackedValueResult<MaybeStale<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: DeserializeOwned + Serialize,
+        Fmt: Clone + Send + 'static,
+    {
+        if let Some(res) = self.get()? {
+            return Ok(MaybeStale::Fresh(res));
+        }
+
+        if let Some(res) = self.get_stale(self.options.stale_while_revalidate)? {
+            // Only one thread refreshes a value at a time, rather than one for every access while it is stale.
+            if let Some(refresh) = Refresh::start(&self.path) {
+                let this = self.clone();
+                thread::spawn(move || {
+                    let _refresh = refresh;
+                    let _ = this.get_or_insert_with(default);
+                });
+            }
+            return Ok(MaybeStale::Stale(res));
+        }
+
+        self.get_or_insert_with(default).map(MaybeStale::Fresh)
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value.
+    pub fn insert<T>(&self, value: &T) -> FileBackedValueResult<()>
+    where
+        T: Serialize
+    {
+        self.write(value, None).map(|_| ())
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, and lets it expire after `ttl`.
+    ///
+    /// This is useful when the lifetime comes from the value itself, such as the `expires_in` of an access token.
+    /// If a dirty time is set as well, the value expires at whichever comes first.
+    /// A `ttl` too large to be represented, such as a bogus `max-age`, does not expire the value.
+    pub fn insert_with_ttl<T>(&self, value: &T, ttl: Duration) -> FileBackedValueResult<()>
+    where
+        T: Serialize
+    {
+        self.write(value, SystemTime::now().checked_add(ttl)).map(|_| ())
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, and lets it expire at `expires`.
+    /// If a dirty time is set as well, the value expires at whichever comes first.
+    pub fn insert_until<T>(&self, value: &T, expires: SystemTime) -> FileBackedValueResult<()>
     where
         T: Serialize
     {
-        write_file(&self.path, value).unwrap();
+        self.write(value, Some(expires)).map(|_| ())
     }
 
-    /// Check whether the backing file was last modified longer than `dirty_time` ago.
-    /// If the file does not exist or the modification time could otherwise not be retrieved, true is returned.
-    fn file_is_dirty(&self) -> bool {
-        self.dirty_time.is_some_and(|dirty_time|
-            file_needs_recomputation(&self.path(), dirty_time))
+    /// Get the current value with its metadata, or None if the backing file does not exist or is dirty.
+    pub(crate) fn get_stored<T>(&self) -> FileBackedValueResult<Option<Stored<T>>>
+    where
+        T: DeserializeOwned
+    {
+        let stored = self.read()?
+            .filter(|stored| !self.options.is_expired(&stored.header));
+        if stored.is_some() {
+            self.record_access();
+        }
+        Ok(stored)
+    }
+
+    /// Read the value and its metadata from the backing file, regardless of whether it is dirty.
+    pub(crate) fn read<T>(&self) -> FileBackedValueResult<Option<Stored<T>>>
+    where
+        T: DeserializeOwned
+    {
+        let (stored, repair) = {
+            let _lock = self.lock(FileLock::shared)?;
+            self.read_unlocked()?
+        };
+
+        if let Some(repair) = repair {
+            let lock = self.lock(FileLock::exclusive)?;
+            self.repair_unlocked(repair, lock.is_some())?;
+        }
+        Ok(stored)
+    }
+
+    /// Like [`FileBackedValue::get_or_try_insert_with`], but also returns the metadata of the value.
+    pub(crate) fn get_or_try_insert_stored_with<F, T, E>(&self, default: F) -> Result<Stored<T>, TryInsertError<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+        T: DeserializeOwned + Serialize,
+    {
+        if let Some(stored) = self.get_stored()? {
+            return Ok(stored);
+        }
+
+        let flight = Flight::of(&self.path);
+        let _flight = flight.join();
+        // Another thread might have inserted the value while we were waiting for it.
+        if let Some(stored) = self.get_stored()? {
+            return Ok(stored);
+        }
+
+        if self.options.lock_policy != LockPolicy::HoldDuringCompute {
+            let value = default().map_err(TryInsertError::Compute)?;
+            let header = self.write(&value, None)?;
+            return Ok(Stored { header, value });
+        }
+
+        let _lock = FileLock::exclusive(&self.path).map_err(FileBackedValueError::from)?;
+        // Another process might have inserted the value while we were waiting for the lock.
+        let (stored, repair) = self.read_unlocked()?;
+        if let Some(repair) = repair {
+            self.repair_unlocked(repair, true)?;
+        }
+        if let Some(stored) = stored.filter(|stored| !self.options.is_expired(&stored.header)) {
+            self.record_access();
+            return Ok(stored);
+        }
+
+        let value = default().map_err(TryInsertError::Compute)?;
+        let header = self.write_unlocked(&value, None)?;
+        Ok(Stored { header, value })
+    }
+
+    /// Writes `value` to the backing file, and returns the metadata that was written with it.
+    /// The value expires at `expires` or after the dirty time, whichever comes first.
+    pub(crate) fn write<T>(&self, value: &T, expires: Option<SystemTime>) -> FileBackedValueResult<Header>
+    where
+        T: Serialize + ?Sized
+    {
+        let _lock = self.lock(FileLock::exclusive)?;
+        self.write_unlocked(value, expires)
+    }
+
+    /// Take a lock on the backing file using `acquire`, unless locking is disabled.
+    fn lock(&self, acquire: fn(&Path) -> io::Result<FileLock>) -> io::Result<Option<FileLock>> {
+        match self.options.lock_policy {
+            LockPolicy::Disabled => Ok(None),
+            LockPolicy::PerOperation | LockPolicy::HoldDuringCompute => acquire(&self.path).map(Some),
+        }
+    }
+
+    /// Get the current value even if it is dirty, provided that it has not been dirty for longer than `max_staleness`.
+    /// If `max_staleness` is None, stale values are never returned.
+    fn get_stale<T>(&self, max_staleness: Option<Duration>) -> FileBackedValueResult<Option<T>>
+    where
+        T: DeserializeOwned
+    {
+        if max_staleness.is_none() {
+            return Ok(None);
+        }
+
+        Ok(self.read()?
+            .filter(|stored| self.options.is_acceptably_stale(&stored.header, max_staleness))
+            .map(|stored| stored.value))
+    }
+
+    /// Read the value and its metadata from the backing file, regardless of whether it is dirty,
+    /// along with the change to the backing file that reading it called for, if any.
+    /// The caller is responsible for locking, and for applying the repair under an exclusive lock.
+    fn read_unlocked<T>(&self) -> FileBackedValueResult<(Option<Stored<T>>, Option<Repair>)>
+    where
+        T: DeserializeOwned
+    {
+        let Some(raw) = read_raw(&self.path)? else {
+            return Ok((None, None));
+        };
+        self.decode_with_repair(raw)
+    }
+
+    /// Deserialize a value from the contents of the backing file, applying the corruption policy,
+    /// along with the change to the backing file that this called for, if any.
+    fn decode_with_repair<T>(&self, raw: RawFile) -> FileBackedValueResult<(Option<Stored<T>>, Option<Repair>)>
+    where
+        T: DeserializeOwned
+    {
+        match self.decode(&raw) {
+            Err(e) if e.is_corruption() => match self.options.corruption_policy {
+                CorruptionPolicy::Error => Err(e),
+                CorruptionPolicy::Recompute => Ok((None, None)),
+                CorruptionPolicy::Quarantine => Ok((None, Some(Repair { read: raw.bytes, action: RepairAction::Quarantine }))),
+            },
+            Err(e) => Err(e),
+            Ok(None) => Ok((None, None)),
+            Ok(Some(Decoded { stored, migrated: None })) => Ok((Some(stored), None)),
+            Ok(Some(Decoded { stored, migrated: Some(bytes) })) => {
+                Ok((Some(stored), Some(Repair { read: raw.bytes, action: RepairAction::Rewrite(bytes) })))
+            }
+        }
+    }
+
+    /// Apply `repair` to the backing file, provided that it still has the contents that were read.
+    ///
+    /// Migrated values are only rewritten while `locked`, that is, while holding the exclusive lock,
+    /// as writers are not excluded otherwise and a newer value could be overwritten.
+    /// Corrupt files are quarantined regardless, as they would otherwise be overwritten by the recomputed value.
+    /// Without the lock, a writer could still replace the file between checking its contents and moving it aside,
+    /// in which case its value is quarantined instead and recomputed by the next reader.
+    fn repair_unlocked(&self, repair: Repair, locked: bool) -> FileBackedValueResult<()> {
+        if !repair.applies(locked) || read_raw(&self.path)?.is_none_or(|raw| raw.bytes != repair.read) {
+            return Ok(());
+        }
+
+        match repair.action {
+            RepairAction::Rewrite(bytes) => write_bytes(&self.path, &bytes),
+            RepairAction::Quarantine => match fs::rename(&self.path, corruption::quarantine_path(&self.path)) {
+                // Another reader might have moved the file aside already.
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// Write `value` to the backing file, and return the metadata that was written with it.
+    /// The caller is responsible for locking.
+    fn write_unlocked<T>(&self, value: &T, expires: Option<SystemTime>) -> FileBackedValueResult<Header>
+    where
+        T: Serialize + ?Sized
+    {
+        let (header, bytes) = self.encode(value, expires, self.options.inputs_digest()?)?;
+        write_bytes(&self.path, &bytes)?;
+        if let Some(store) = &self.options.store {
+            store.record_write(&self.path)?;
+        }
+        Ok(header)
+    }
+
+    /// Record that the value was read in the store it belongs to, if any.
+    pub(crate) fn record_access(&self) {
+        if let Some(store) = &self.options.store {
+            // Access times only decide what is evicted first, which is not worth failing a read over.
+            let _ = store.record_access(&self.path);
+        }
+    }
+
+    /// Serialize `value` as it is stored in the backing file, along with new metadata.
+    /// The value expires at `expires` or after the dirty time, whichever comes first,
+    /// and `inputs` is the current digest of the input files, if any.
+    fn encode<T>(&self, value: &T, expires: Option<SystemTime>, inputs: Option<String>) -> FileBackedValueResult<(Header, Vec<u8>)>
+    where
+        T: Serialize + ?Sized
+    {
+        let created = SystemTime::now();
+        let header = Header {
+            created,
+            expires: self.options.expires_at(created, expires),
+            schema: self.options.schema_version,
+            inputs,
+        };
+        let bytes = self.pack(envelope::encode(&self.format, &header, value)?)?;
+        Ok((header, bytes))
+    }
+
+    /// Deserialize a value and its metadata from the contents of the backing file.
+    /// Returns None if the value has an older schema version that does not match `T`, and cannot be migrated either,
+    /// or if it was written without the configured encryption or checksum.
+    ///
+    /// A value of a newer schema version results in [`FileBackedValueError::NewerSchema`], whether or not
+    /// migrations are registered, as it was written by a newer version of the program and must not be
+    /// overwritten with a value of an older schema.
+    fn decode<T>(&self, raw: &RawFile) -> FileBackedValueResult<Option<Decoded<T>>>
+    where
+        T: DeserializeOwned
+    {
+        let Some(payload) = self.unpack(&raw.bytes)? else {
+            return Ok(None);
+        };
+        let decoded = envelope::decode::<T, _>(&self.format, &payload, raw.modified);
+        let schema_version = self.options.schema_version;
+        if decoded.as_ref().is_ok_and(|stored| stored.header.schema == schema_version) {
+            return decoded.map(|stored| Some(Decoded { stored, migrated: None }));
+        }
+
+        // The value might not match `T`, in which case only the leading fields tell which schema version it has.
+        let stored_schema = match &decoded {
+            Ok(stored) => Some(stored.header.schema),
+            Err(_) => envelope::decode_schema(&self.format, &payload),
+        };
+        if let Some(stored_schema) = stored_schema.filter(|&stored_schema| stored_schema > schema_version) {
+            return Err(FileBackedValueError::NewerSchema(stored_schema));
+        }
+
+        if self.options.migrations.is_empty() {
+            // Values of an older schema version are dirty, so one that no longer matches `T` is as good as missing.
+            return match decoded {
+                Ok(stored) => Ok(Some(Decoded { stored, migrated: None })),
+                Err(_) if stored_schema.is_some_and(|stored_schema| stored_schema != schema_version) => Ok(None),
+                Err(e) => Err(e),
+            };
+        }
+
+        // The value of an older schema version most likely does not match `T`, so it is migrated in its generic form.
+        let Ok(stored) = envelope::decode::<serde_json::Value, _>(&self.format, &payload, raw.modified) else {
+            return decoded.map(|stored| Some(Decoded { stored, migrated: None }));
+        };
+
+        if stored.header.schema == schema_version {
+            return decoded.map(|stored| Some(Decoded { stored, migrated: None }));
+        }
+
+        let value = self.options.migrations.migrate(stored.header.schema, schema_version, stored.value)?;
+        let header = Header { schema: schema_version, ..stored.header };
+        let bytes = self.pack(envelope::encode(&self.format, &header, &value)?)?;
+        let value = serde_json::from_value(value)?;
+        Ok(Some(Decoded { stored: Stored { header, value }, migrated: Some(bytes) }))
+    }
+
+    /// Turn a serialized envelope into the contents of the backing file, by adding the configured checksum
+    /// and compressing it.
+    fn pack(&self, payload: Vec<u8>) -> FileBackedValueResult<Vec<u8>> {
+        let payload = match self.options.checksum {
+            Some(checksum) => checksum::frame(checksum, &payload),
+            None => payload,
+        };
+
+        let payload = match self.options.compression {
+            Some(compression) => compression::compress(compression, &payload)?,
+            None => payload,
+        };
+
+        #[cfg(feature = "encryption")]
+        let payload = match &self.options.encryption {
+            Some(encryption) => encryption.encrypt(&payload, self.filename_bytes())?,
+            None => payload,
+        };
+
+        Ok(payload)
+    }
+
+    /// Turn the contents of the backing file back into a serialized envelope, by decrypting it if encryption is
+    /// configured, decompressing it if it is compressed, and verifying its checksum if one is stored or configured.
+    ///
+    /// Returns None if the file was written without the configured encryption or checksum, in which case the value
+    /// is dirty.
+    fn unpack<'a>(&self, bytes: &'a [u8]) -> FileBackedValueResult<Option<Cow<'a, [u8]>>> {
+        let Some(bytes) = self.decrypt(bytes)? else {
+            return Ok(None);
+        };
+
+        let bytes = and_then_cow(bytes, compression::decompress)?;
+        if self.options.checksum.is_some() && !checksum::is_framed(&bytes) {
+            return Ok(None);
+        }
+        and_then_cow(bytes, |bytes| checksum::unframe(bytes).map(Cow::Borrowed)).map(Some)
+    }
+
+    /// Decrypt the contents of the backing file if encryption is configured, or None if they are not encrypted.
+    /// Plaintext contents are never returned under a key, such that they are recomputed and overwritten encrypted.
+    /// Encrypted contents without a key are an error, even without the `encryption` feature,
+    /// as they must not be handled by the corruption policy.
+    fn decrypt<'a>(&self, bytes: &'a [u8]) -> FileBackedValueResult<Option<Cow<'a, [u8]>>> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = &self.options.encryption {
+            if !encryption::is_encrypted(bytes) {
+                return Ok(None);
+            }
+            return encryption.decrypt(bytes, self.filename_bytes()).map(|bytes| Some(Cow::Owned(bytes)));
+        }
+
+        if encryption::is_encrypted(bytes) {
+            return Err(FileBackedValueError::DecryptionFailed);
+        }
+        Ok(Some(Cow::Borrowed(bytes)))
+    }
+
+    /// Name of the backing file, to which its encrypted contents are bound.
+    #[cfg(feature = "encryption")]
+    fn filename_bytes(&self) -> &[u8] {
+        self.path.file_name().unwrap_or_default().as_encoded_bytes()
     }
 }
 
-/// Read a value of type `T` from the backing file as a JSON string.
-fn read_file<T>(path: &PathBuf) -> FileBackedValueResult<Option<T>>
-where
-    T: DeserializeOwned
-{
-    match fs::OpenOptions::new().read(true).open(path) {
-        Ok(f) => {
-            let rdr = BufReader::new(f);
-            serde_json::from_reader(rdr)
-                .map_err(Into::into)
-                .map(|json| Some(json))
-        },
-        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
-        Err(e) => Err(FileBackedValueError::FileError(e))
+impl Options {
+    /// When a value that was created at `created` expires, if ever.
+    /// This is the earliest of `expires`, the current dirty time, and the expiry policy.
+    /// A dirty time too large to be represented never expires, like [`Ttl`].
+    fn expires_at(&self, created: SystemTime, expires: Option<SystemTime>) -> Option<SystemTime> {
+        let dirty_at = self.dirty_time.and_then(|dirty_time| created.checked_add(dirty_time));
+        let policy_at = self.expiry.as_ref().and_then(|policy| policy.expires_at(created));
+        expiry::earliest(expiry::earliest(expires, dirty_at), policy_at)
+    }
+
+    /// How long ago a value with the given metadata expired, or None if it has not expired.
+    fn staleness(&self, header: &Header) -> Option<Duration> {
+        let expires_at = self.expires_at(header.created, header.expires)?;
+        SystemTime::now().duration_since(expires_at).ok()
+    }
+
+    /// Whether a value with the given metadata is dirty and needs to be recomputed.
+    /// Values of another schema version, or derived from input files that have since changed, are always dirty.
+    fn is_expired(&self, header: &Header) -> bool {
+        self.is_expired_regardless_of_inputs(header)
+            || inputs_changed(header, self.inputs_digest())
+    }
+
+    /// Whether a value with the given metadata is dirty, without checking whether its input files have changed,
+    /// which is the expensive part.
+    fn is_expired_regardless_of_inputs(&self, header: &Header) -> bool {
+        header.schema != self.schema_version
+            || self.staleness(header).is_some()
+    }
+
+    /// Digest of the current contents of the input files, or None if there are none.
+    fn inputs_digest(&self) -> io::Result<Option<String>> {
+        if self.inputs.is_empty() {
+            return Ok(None);
+        }
+
+        self.inputs.digest().map(Some)
+    }
+
+    /// Whether a dirty value with the given metadata has been dirty for at most `max_staleness`.
+    fn is_acceptably_stale(&self, header: &Header, max_staleness: Option<Duration>) -> bool {
+        header.schema == self.schema_version
+            && self.staleness(header).zip(max_staleness)
+                .is_some_and(|(staleness, max_staleness)| staleness <= max_staleness)
     }
 }
 
-/// Write `value` to the backing file as a JSON string.
-fn write_file<T>(path: &PathBuf, value: &T) -> FileBackedValueResult<()>
-where
-    T: Serialize
-{
+impl<T> MaybeStale<T> {
+    /// The value, regardless of whether it is stale.
+    pub fn into_inner(self) -> T {
+        match self {
+            MaybeStale::Fresh(value) | MaybeStale::Stale(value) => value,
+        }
+    }
+
+    /// Whether the value has expired.
+    pub fn is_stale(&self) -> bool {
+        matches!(self, MaybeStale::Stale(_))
+    }
+}
+
+/// Whether the input files have changed since a value with the given metadata was written,
+/// given their current `digest`. Input files that cannot be read are assumed to have changed.
+fn inputs_changed(header: &Header, digest: io::Result<Option<String>>) -> bool {
+    match digest {
+        Ok(digest) => digest.is_some() && digest != header.inputs,
+        Err(_) => true,
+    }
+}
+
+/// A change to the backing file that reading it called for, which is only made if the file is unchanged since.
+struct Repair {
+    /// The contents of the backing file when it was read.
+    read: Vec<u8>,
+    action: RepairAction,
+}
+
+impl Repair {
+    /// Whether the repair should be made, depending on whether the exclusive lock is held.
+    fn applies(&self, locked: bool) -> bool {
+        match self.action {
+            RepairAction::Rewrite(_) => locked,
+            RepairAction::Quarantine => true,
+        }
+    }
+}
+
+/// How the backing file is changed by a [`Repair`].
+enum RepairAction {
+    /// Replace the value with the one that was migrated to the current schema version.
+    Rewrite(Vec<u8>),
+    /// Move the corrupt backing file aside, as configured by [`CorruptionPolicy::Quarantine`].
+    Quarantine,
+}
+
+/// Contents of a backing file, along with its modification time.
+struct RawFile {
+    bytes: Vec<u8>,
+    modified: Option<SystemTime>,
+}
+
+/// A value decoded from a backing file.
+struct Decoded<T> {
+    stored: Stored<T>,
+    /// The new contents of the backing file, if the value was migrated to the current schema version.
+    migrated: Option<Vec<u8>>,
+}
+
+/// Read the contents of the file at `path`, or None if it does not exist.
+fn read_raw(path: &Path) -> FileBackedValueResult<Option<RawFile>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(FileBackedValueError::FileError(e)),
+    };
+
+    let metadata = file.metadata()?;
+    let mut bytes = Vec::with_capacity(metadata.len().try_into().unwrap_or(0));
+    file.read_to_end(&mut bytes)?;
+    Ok(Some(RawFile {
+        bytes,
+        modified: metadata.modified().or_else(|_| metadata.created()).ok(),
+    }))
+}
+
+/// Write `bytes` to the file at `path`.
+///
+/// The bytes are first written to a temporary sibling file, which is synced to disk and then
+/// atomically renamed over `path`. Readers will therefore only ever see either the old or the new value,
+/// even if the process crashes halfway through writing.
+fn write_bytes(path: &Path, bytes: &[u8]) -> FileBackedValueResult<()> {
     // Create parent directories if necessary.
-    if let Some(dir) = path.parent() {
-        fs::create_dir_all(dir).map_err(Into::into)?;
+    let dir = parent_dir(path);
+    fs::create_dir_all(dir)?;
+
+    let tmp_path = temp_path(path);
+    let res = write_temp_file(&tmp_path, bytes)
+        .and_then(|()| fs::rename(&tmp_path, path));
+    if res.is_err() {
+        // Do not leave partially written temporary files behind.
+        let _ = fs::remove_file(&tmp_path);
     }
+    res?;
+
+    // Make sure the rename itself is persisted.
+    sync_dir(dir)
+}
 
-    let file = fs::OpenOptions::new()
+/// Write `bytes` to a new file at `tmp_path`, and flush it to disk.
+fn write_temp_file(tmp_path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
         .create_new(true)
         .write(true)
-        .truncate(true)
-        .open(path)
-        .map_err(Into::into)?;
+        .open(tmp_path)?;
+
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Apply one step of unpacking to `bytes`, keeping the result borrowed from the backing file where possible.
+fn and_then_cow<'a, F>(bytes: Cow<'a, [u8]>, f: F) -> FileBackedValueResult<Cow<'a, [u8]>>
+where
+    F: for<'b> FnOnce(&'b [u8]) -> FileBackedValueResult<Cow<'b, [u8]>>
+{
+    match bytes {
+        Cow::Borrowed(bytes) => f(bytes),
+        Cow::Owned(bytes) => f(&bytes).map(|bytes| Cow::Owned(bytes.into_owned())),
+    }
+}
 
-    let wtr = BufWriter::new(file);
-    serde_json::to_writer(wtr, value).map_err(Into::into)
+/// Directory containing the file at `path`.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
 }
 
-/// Check whether the file at `path` was last modified longer than `dirty_time` ago.
-/// If the file does not exist or the modification time could otherwise not be retrieved, true is returned.
-fn file_needs_recomputation(path: &PathBuf, dirty_time: Duration) -> bool {
-    time_since_modified(path).is_none_or(|last_modified|
-        last_modified >= dirty_time)
+/// A unique temporary path in the same directory as `path`,
+/// such that it can be atomically renamed to `path` later.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut filename = OsString::from(".");
+    filename.push(path.file_name().unwrap_or_default());
+    filename.push(format!(".{}.{}.tmp", process::id(), count));
+    path.with_file_name(filename)
 }
 
-/// Get the duration since the file at `path` was last modified.
-fn time_since_modified(path: &PathBuf) -> Option<Duration> {
-    if let Ok(time) = fs::metadata(path) {
-        let now = SystemTime::now();
-        let last_modified = time.modified().or_else(|_| time.created()).ok()?;
-        now.duration_since(last_modified).ok()
-    } else {
-        None
+/// Flush the directory entries of `dir` to disk.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> FileBackedValueResult<()> {
+    fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Directories cannot be opened as files on this platform, so there is nothing to sync.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> FileBackedValueResult<()> {
+    Ok(())
+}
+
+impl From<io::Error> for FileBackedValueError {
+    fn from(e: io::Error) -> Self {
+        FileBackedValueError::FileError(e)
     }
 }
 
-impl Into<FileBackedValueError> for io::Error {
-    fn into(self) -> FileBackedValueError {
-        FileBackedValueError::FileError(self)
+impl From<serde_json::Error> for FileBackedValueError {
+    fn from(e: serde_json::Error) -> Self {
+        FileBackedValueError::JsonError(e)
     }
 }
 
-impl Into<FileBackedValueError> for serde_json::Error {
-    fn into(self) -> FileBackedValueError {
-        FileBackedValueError::JsonError(self)
+impl fmt::Display for FileBackedValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileBackedValueError::FileError(_) => write!(f, "failed to access the backing file"),
+            FileBackedValueError::JsonError(_) => write!(f, "failed to serialize or deserialize the value as JSON"),
+            FileBackedValueError::FormatError(_) => write!(f, "failed to serialize or deserialize the value"),
+            FileBackedValueError::InvalidConfig(message) => write!(f, "invalid configuration: {message}"),
+            FileBackedValueError::NoHomeDirectory => write!(f, "no valid home directory found, and {DIR_ENV_VAR} is not set"),
+            FileBackedValueError::NoBaseDirectory(base_dir) => {
+                write!(f, "no {base_dir:?} directory on this platform, and {DIR_ENV_VAR} is not set")
+            }
+            FileBackedValueError::UnsupportedEnvelope(version) => write!(f, "unsupported envelope version {version}"),
+            FileBackedValueError::MissingMigration(from) => write!(f, "no migration from schema version {from}"),
+            FileBackedValueError::NewerSchema(version) => write!(f, "stored value has newer schema version {version}"),
+            FileBackedValueError::ChecksumMismatch => write!(f, "checksum of the backing file does not match"),
+            FileBackedValueError::CompressionError(_) => write!(f, "failed to compress or decompress the backing file"),
+            FileBackedValueError::UnsupportedCompression(name) => {
+                write!(f, "backing file is compressed with {name}, but the `{name}` feature is not enabled")
+            }
+            FileBackedValueError::DecryptionFailed => write!(f, "failed to decrypt the backing file"),
+            FileBackedValueError::EncryptionFailed => write!(f, "failed to encrypt the value"),
+            FileBackedValueError::KeyUnavailable(_) => write!(f, "encryption key is unavailable"),
+        }
+    }
+}
+
+impl Error for FileBackedValueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FileBackedValueError::FileError(e) | FileBackedValueError::CompressionError(e) => Some(e),
+            FileBackedValueError::JsonError(e) => Some(e),
+            FileBackedValueError::FormatError(e) | FileBackedValueError::KeyUnavailable(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl<E> fmt::Display for TryInsertError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryInsertError::Compute(_) => write!(f, "failed to compute the value"),
+            TryInsertError::Storage(_) => write!(f, "failed to read or write the backing file"),
+        }
+    }
+}
+
+impl<E> Error for TryInsertError<E>
+where
+    E: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TryInsertError::Compute(e) => Some(e),
+            TryInsertError::Storage(e) => Some(e),
+        }
+    }
+}
+
+impl<E> From<FileBackedValueError> for TryInsertError<E> {
+    fn from(e: FileBackedValueError) -> Self {
+        TryInsertError::Storage(e)
+    }
+}
+
+impl TryInsertError<Infallible> {
+    /// The storage error, as an infallible computation cannot have failed.
+    pub(crate) fn into_storage(self) -> FileBackedValueError {
+        match self {
+            TryInsertError::Compute(e) => match e {},
+            TryInsertError::Storage(e) => e,
+        }
+    }
+}
+
+impl FileBackedValueError {
+    /// Whether this error means that the contents of a backing file are invalid, as handled by [`CorruptionPolicy`].
+    fn is_corruption(&self) -> bool {
+        matches!(self, FileBackedValueError::JsonError(_)
+            | FileBackedValueError::FormatError(_)
+            | FileBackedValueError::ChecksumMismatch
+            | FileBackedValueError::CompressionError(_))
+    }
+
+    /// Wrap an error of one of the non-JSON formats.
+    #[allow(dead_code, reason = "only used when a non-JSON format is enabled")]
+    fn format<E>(e: E) -> Self
+    where
+        E: Error + Send + Sync + 'static
+    {
+        FileBackedValueError::FormatError(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migration_does_not_overwrite_a_newer_value() {
+        let dir = tempfile::tempdir().unwrap();
+        FileBackedValue::builder("value").dir(dir.path()).build().unwrap().insert(&1u32).unwrap();
+
+        let value = FileBackedValue::builder("value").dir(dir.path())
+            .schema_version(1)
+            .migration(0, |value| serde_json::json!(value.as_u64().unwrap() + 1))
+            .lock_policy(LockPolicy::PerOperation)
+            .build().unwrap();
+        let (stored, repair) = value.read_unlocked::<u32>().unwrap();
+        assert_eq!(stored.unwrap().value, 2);
+
+        // Another writer replaces the value between reading it and rewriting the migrated one.
+        value.insert(&10u32).unwrap();
+        value.repair_unlocked(repair.unwrap(), true).unwrap();
+        assert_eq!(value.get::<u32>().unwrap(), Some(10));
+    }
+
+    #[test]
+    fn quarantine_does_not_move_a_file_rewritten_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = FileBackedValue::builder("value").dir(dir.path())
+            .corruption_policy(CorruptionPolicy::Quarantine)
+            .build().unwrap();
+        fs::write(value.path(), "not a value").unwrap();
+        let (stored, repair) = value.read_unlocked::<u32>().unwrap();
+        assert!(stored.is_none());
+
+        // Another writer replaces the corrupt file between reading it and moving it aside.
+        value.insert(&1u32).unwrap();
+        value.repair_unlocked(repair.unwrap(), false).unwrap();
+        assert_eq!(value.get::<u32>().unwrap(), Some(1));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
     }
 }
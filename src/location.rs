@@ -0,0 +1,106 @@
+use std::{env, path::{Path, PathBuf}};
+
+use directories::{BaseDirs, ProjectDirs};
+
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+/// Environment variable which, when set, overrides the directory of all values that are not given an explicit directory.
+pub const DIR_ENV_VAR: &str = "FILE_BACKED_VALUE_DIR";
+
+/// Kind of standard directory in which a value is stored, following the conventions of the platform.
+///
+/// Values with a dirty time can usually be recomputed and belong in [`BaseDir::Cache`],
+/// while settings belong in [`BaseDir::Config`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BaseDir {
+    /// For example `/home/alice/.local/share` on Linux.
+    #[default]
+    Data,
+    /// For example `/home/alice/.cache` on Linux.
+    Cache,
+    /// For example `/home/alice/.config` on Linux.
+    Config,
+    /// For example `/home/alice/.local/state` on Linux. Not available on MacOS and Windows.
+    State,
+    /// For example `/run/user/1000` on Linux. Not available on MacOS and Windows.
+    Runtime,
+}
+
+/// Identifies an application, such that its values are stored in an application-specific subdirectory
+/// of the [`BaseDir`], for example `/home/alice/.cache/barapp` on Linux.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Project {
+    qualifier: String,
+    organization: String,
+    application: String,
+}
+
+impl Project {
+    /// See [`ProjectDirs::from`] for how these are combined on each platform.
+    pub fn new(qualifier: &str, organization: &str, application: &str) -> Self {
+        Self {
+            qualifier: qualifier.to_owned(),
+            organization: organization.to_owned(),
+            application: application.to_owned(),
+        }
+    }
+}
+
+impl BaseDir {
+    /// Name of the subdirectory of [`DIR_ENV_VAR`] in which values of this kind are stored.
+    fn name(self) -> &'static str {
+        match self {
+            BaseDir::Data => "data",
+            BaseDir::Cache => "cache",
+            BaseDir::Config => "config",
+            BaseDir::State => "state",
+            BaseDir::Runtime => "runtime",
+        }
+    }
+}
+
+/// Directory of values that are not given an explicit directory.
+/// This is the requested standard directory, or a subdirectory of the [`DIR_ENV_VAR`] environment variable
+/// named after the kind of directory if it is set, such that values of different kinds do not overwrite each other.
+/// Values of a `project` are stored in a subdirectory named after its application in either case,
+/// such that applications sharing the environment variable do not overwrite each other's values.
+pub(crate) fn default_dir(base_dir: BaseDir, project: Option<&Project>) -> FileBackedValueResult<PathBuf> {
+    if let Some(dir) = env::var_os(DIR_ENV_VAR).filter(|dir| !dir.is_empty()) {
+        let dir = PathBuf::from(dir);
+        let dir = match project {
+            Some(project) => dir.join(sanitize_filename::sanitize(&project.application)),
+            None => dir,
+        };
+        return Ok(dir.join(base_dir.name()));
+    }
+
+    let dir = if let Some(project) = project {
+        let dirs = ProjectDirs::from(&project.qualifier, &project.organization, &project.application)
+            .ok_or(FileBackedValueError::NoHomeDirectory)?;
+        select(base_dir, dirs.data_dir(), dirs.cache_dir(), dirs.config_dir(), dirs.state_dir(), dirs.runtime_dir())
+    } else {
+        let dirs = BaseDirs::new()
+            .ok_or(FileBackedValueError::NoHomeDirectory)?;
+        select(base_dir, dirs.data_dir(), dirs.cache_dir(), dirs.config_dir(), dirs.state_dir(), dirs.runtime_dir())
+    };
+
+    dir.ok_or(FileBackedValueError::NoBaseDirectory(base_dir))
+}
+
+/// The directory of the requested kind, out of the directories that the platform provides.
+fn select(
+    base_dir: BaseDir,
+    data: &Path,
+    cache: &Path,
+    config: &Path,
+    state: Option<&Path>,
+    runtime: Option<&Path>,
+) -> Option<PathBuf> {
+    match base_dir {
+        BaseDir::Data => Some(data),
+        BaseDir::Cache => Some(cache),
+        BaseDir::Config => Some(config),
+        BaseDir::State => state,
+        BaseDir::Runtime => runtime,
+    }.map(Path::to_path_buf)
+}
@@ -0,0 +1,120 @@
+use std::{ffi::OsString, fs, io, path::{Path, PathBuf}};
+
+/// When to take advisory locks on the backing file, to coordinate access between processes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Do not lock the backing file.
+    #[default]
+    Disabled,
+    /// Take a shared lock while reading the backing file, and an exclusive lock while writing it.
+    PerOperation,
+    /// Like [`LockPolicy::PerOperation`], but additionally hold the exclusive lock while
+    /// `get_or_insert_with` computes a missing value, such that only one process computes it.
+    HoldDuringCompute,
+}
+
+/// An advisory lock on the lock file belonging to a backing file, which is released when dropped.
+///
+/// A separate lock file is used because writes replace the backing file by renaming over it,
+/// so a lock on the backing file itself would not be seen by processes that open it afterwards.
+#[derive(Debug)]
+pub(crate) struct FileLock {
+    file: fs::File,
+}
+
+impl FileLock {
+    /// Block until a shared lock is acquired on the lock file of `path`.
+    pub(crate) fn shared(path: &Path) -> io::Result<Self> {
+        let file = open_lock_file(path)?;
+        file.lock_shared()?;
+        Ok(Self { file })
+    }
+
+    /// Block until an exclusive lock is acquired on the lock file of `path`.
+    pub(crate) fn exclusive(path: &Path) -> io::Result<Self> {
+        let file = open_lock_file(path)?;
+        file.lock()?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock as well, so an error here is harmless.
+        let _ = self.file.unlock();
+    }
+}
+
+/// Path of the lock file belonging to the backing file at `path`.
+pub(crate) fn lock_path(path: &Path) -> PathBuf {
+    let mut filename = OsString::from(".");
+    filename.push(path.file_name().unwrap_or_default());
+    filename.push(".lock");
+    path.with_file_name(filename)
+}
+
+/// Open or create the lock file of `path`, creating parent directories if necessary.
+fn open_lock_file(path: &Path) -> io::Result<fs::File> {
+    let lock_path = lock_path(path);
+    if let Some(dir) = lock_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::TryLockError;
+
+    use super::*;
+
+    fn try_lock(path: &Path, exclusive: bool) -> bool {
+        let file = open_lock_file(path).unwrap();
+        let res = if exclusive { file.try_lock() } else { file.try_lock_shared() };
+        match res {
+            Ok(()) => true,
+            Err(TryLockError::WouldBlock) => false,
+            Err(TryLockError::Error(e)) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn exclusive_lock_excludes_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("value");
+
+        let lock = FileLock::exclusive(&path).unwrap();
+        assert!(!try_lock(&path, false));
+        assert!(!try_lock(&path, true));
+
+        drop(lock);
+        assert!(try_lock(&path, true));
+    }
+
+    #[test]
+    fn shared_locks_exclude_writers_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("value");
+
+        let _first = FileLock::shared(&path).unwrap();
+        let _second = FileLock::shared(&path).unwrap();
+        assert!(try_lock(&path, false));
+        assert!(!try_lock(&path, true));
+    }
+
+    #[test]
+    fn lock_file_is_hidden_next_to_the_backing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("value");
+
+        let _lock = FileLock::exclusive(&path).unwrap();
+        assert_eq!(lock_path(&path), dir.path().join("nested").join(".value.lock"));
+        assert!(lock_path(&path).exists());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fs, io, marker::PhantomData, path::{Path, PathBuf}, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{ExpiryPolicy, FileBackedValue, FileBackedValueResult, Format, Json, LockPolicy};
+
+/// A map of file-backed values, for example to memoize a function over its arguments.
+///
+/// Each entry is stored in its own file in a directory, named after a hash of the serialized key.
+/// Keys must therefore serialize deterministically; a `HashMap` for example does not.
+/// All entries share the configuration of the map, such as its format and dirty time.
+#[derive(Clone, Debug)]
+pub struct FileBackedMap<K, V, Fmt = Json> {
+    /// The path of this value is the directory of the entries.
+    inner: FileBackedValue<Fmt>,
+    _entries: PhantomData<fn(K) -> V>,
+}
+
+/// An entry as it is stored in its backing file. The key is stored as well, such that the map can be iterated.
+#[derive(Serialize)]
+struct EntryRef<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+impl<K, V> FileBackedMap<K, V>
+where
+    K: DeserializeOwned + Serialize,
+    V: DeserializeOwned + Serialize,
+{
+    /// Create a file-backed map in a directory named `dirname` in the user's data directory.
+    ///
+    /// ### Panics
+    /// If no valid home directory is found. See [`FileBackedMap::try_new`] for a non-panicking version.
+    pub fn new(dirname: &str) -> Self {
+        FileBackedValue::new(dirname).into()
+    }
+
+    /// Create a file-backed map in a directory named `dirname` in the user's data directory,
+    /// or return an error if no valid home directory is found.
+    pub fn try_new(dirname: &str) -> FileBackedValueResult<Self> {
+        FileBackedValue::try_new(dirname).map(Into::into)
+    }
+
+    /// Create a file-backed map in a directory named `dirname` in the specified directory.
+    pub fn new_at(dirname: &str, parent: &Path) -> Self {
+        FileBackedValue::new_at(dirname, parent).into()
+    }
+}
+
+impl<K, V, Fmt> FileBackedMap<K, V, Fmt>
+where
+    K: DeserializeOwned + Serialize,
+    V: DeserializeOwned + Serialize,
+    Fmt: Format + Clone,
+{
+    /// Set the duration after which entries are considered dirty and need to be recomputed.
+    pub fn set_dirty_time(&mut self, dirty_time: Duration) {
+        self.inner.set_dirty_time(dirty_time);
+    }
+
+    /// Set the policy that decides when entries are considered dirty and need to be recomputed.
+    pub fn set_expiry(&mut self, policy: impl ExpiryPolicy + 'static) {
+        self.inner.set_expiry(policy);
+    }
+
+    /// Set when to take advisory locks on the backing files, to coordinate access between processes.
+    pub fn set_lock_policy(&mut self, lock_policy: LockPolicy) {
+        self.inner.set_lock_policy(lock_policy);
+    }
+
+    /// Path to the directory containing the backing files of the entries.
+    pub fn dir(&self) -> &PathBuf {
+        self.inner.path()
+    }
+
+    /// Get the value of `key`, which might be None if it was never inserted or is dirty.
+    pub fn get(&self, key: &K) -> FileBackedValueResult<Option<V>> {
+        Ok(self.entry(key)?
+            .get::<Entry<K, V>>()?
+            .map(|entry| entry.value))
+    }
+
+    /// Get the value of `key`, computing and inserting it using `default` if it is missing or dirty.
+    pub fn get_or_insert_with<F>(&self, key: &K, default: F) -> FileBackedValueResult<V>
+    where
+        F: FnOnce() -> V,
+        K: Clone,
+    {
+        let entry = self.entry(key)?
+            .get_or_insert_with(|| Entry { key: key.clone(), value: default() })?;
+        Ok(entry.value)
+    }
+
+    /// Writes `value` as the value of `key`, replacing any existing value.
+    pub fn insert(&self, key: &K, value: &V) -> FileBackedValueResult<()> {
+        self.entry(key)?.insert(&EntryRef { key, value })
+    }
+
+    /// Remove the value of `key`, if any.
+    pub fn remove(&self, key: &K) -> FileBackedValueResult<()> {
+        match self.entry(key)?.clear() {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Iterate over the entries of the map that are not dirty, in no particular order.
+    pub fn iter(&self) -> FileBackedValueResult<impl Iterator<Item = FileBackedValueResult<(K, V)>> + '_> {
+        let dir_entries = match fs::read_dir(self.dir()) {
+            Ok(dir_entries) => Some(dir_entries),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(dir_entries.into_iter().flatten().filter_map(|dir_entry| {
+            let path = match dir_entry {
+                Ok(dir_entry) => dir_entry.path(),
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            // Entries are named after a hash, so this skips lock files, temporary files and quarantined files.
+            if path.file_name().is_none_or(|name| name.as_encoded_bytes().contains(&b'.')) {
+                return None;
+            }
+
+            self.entry_at(path)
+                .get::<Entry<K, V>>()
+                .map(|entry| entry.map(|entry| (entry.key, entry.value)))
+                .transpose()
+        }))
+    }
+
+    /// The file-backed value storing the entry of `key`.
+    fn entry(&self, key: &K) -> FileBackedValueResult<FileBackedValue<Fmt>> {
+        Ok(self.entry_at(self.dir().join(key_filename(key)?)))
+    }
+
+    /// The file-backed value storing the entry at `path`.
+    fn entry_at(&self, path: PathBuf) -> FileBackedValue<Fmt> {
+        FileBackedValue {
+            path,
+            format: self.inner.format.clone(),
+            options: self.inner.options.clone(),
+        }
+    }
+}
+
+impl<K, V, Fmt> From<FileBackedValue<Fmt>> for FileBackedMap<K, V, Fmt> {
+    /// Use the path of `inner` as the directory of the entries, and its configuration for every entry.
+    fn from(inner: FileBackedValue<Fmt>) -> Self {
+        Self { inner, _entries: PhantomData }
+    }
+}
+
+/// Name of the backing file of the entry of `key`, which is a hash of its serialized form.
+pub(crate) fn key_filename<K>(key: &K) -> FileBackedValueResult<String>
+where
+    K: Serialize + ?Sized
+{
+    let key = serde_json::to_vec(key)?;
+    Ok(blake3::hash(&key).to_hex().to_string())
+}
@@ -0,0 +1,51 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{location, map, BaseDir, FileBackedValue, FileBackedValueResult};
+
+/// Identify a call of a memoized function by its arguments.
+pub fn key<K>(args: &K) -> FileBackedValueResult<String>
+where
+    K: Serialize + ?Sized
+{
+    map::key_filename(args)
+}
+
+/// Get the stored result of the memoized function `name` for the arguments identified by `key`,
+/// computing and storing it using `compute` if it is missing or dirty.
+///
+/// Storage errors are ignored, in which case the result is simply computed.
+/// Unlike `get_or_insert_with`, concurrent calls are not coalesced, as a failed write must not lose the computed result.
+pub fn memoize<T, F>(dir: Option<&str>, name: &str, ttl: Option<Duration>, key: FileBackedValueResult<String>, compute: F) -> T
+where
+    T: DeserializeOwned + Serialize,
+    F: FnOnce() -> T,
+{
+    let value = key.and_then(|key| {
+        let dir = match dir {
+            Some(dir) => PathBuf::from(dir),
+            None => location::default_dir(BaseDir::Cache, None)?,
+        };
+
+        // Module paths contain `::`, which is not allowed in filenames on every platform.
+        let mut builder = FileBackedValue::builder(&key)
+            .dir(dir.join(sanitize_filename::sanitize(name.replace("::", "."))));
+        if let Some(ttl) = ttl {
+            builder = builder.dirty_time(ttl);
+        }
+        builder.build()
+    });
+
+    let Ok(value) = value else {
+        return compute();
+    };
+
+    if let Ok(Some(result)) = value.get() {
+        return result;
+    }
+
+    let result = compute();
+    let _ = value.insert(&result);
+    result
+}
@@ -0,0 +1,45 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{FileBackedValueError, FileBackedValueResult};
+
+/// A step that upgrades a value from one schema version to the next.
+type Step = Arc<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// Migrations of stored values to newer schema versions, by the version they upgrade from.
+#[derive(Clone, Default)]
+pub(crate) struct Migrations {
+    steps: BTreeMap<u32, Step>,
+}
+
+impl Migrations {
+    /// Register `step`, which upgrades a value of schema version `from` to version `from + 1`.
+    pub(crate) fn insert(&mut self, from: u32, step: impl Fn(Value) -> Value + Send + Sync + 'static) {
+        self.steps.insert(from, Arc::new(step));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The newest schema version that is upgraded from, if any.
+    pub(crate) fn latest(&self) -> Option<u32> {
+        self.steps.keys().next_back().copied()
+    }
+
+    /// Upgrade `value` from schema version `from` to version `to`, one step at a time.
+    pub(crate) fn migrate(&self, from: u32, to: u32, value: Value) -> FileBackedValueResult<Value> {
+        (from..to).try_fold(value, |value, version| {
+            let step = self.steps.get(&version)
+                .ok_or(FileBackedValueError::MissingMigration(version))?;
+            Ok(step(value))
+        })
+    }
+}
+
+impl fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.steps.keys()).finish()
+    }
+}
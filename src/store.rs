@@ -0,0 +1,325 @@
+use std::{collections::{BTreeMap, HashSet}, fs, io, path::{Path, PathBuf}, sync::{Arc, Mutex, PoisonError}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{envelope::to_millis, lock::FileLock, read_raw, write_bytes, FileBackedValueResult};
+
+/// Name of the file in which a store keeps track of when its entries were accessed.
+/// It is hidden, like lock files and temporary files, such that it is not mistaken for an entry.
+const INDEX_FILENAME: &str = ".store-index.json";
+
+/// How long accesses are kept in memory before they are written to the index, unless a value is written sooner.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Which entries a [`FileBackedStore`] evicts first when it exceeds its limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the entries that were accessed longest ago.
+    #[default]
+    LeastRecentlyUsed,
+    /// Evict the entries that were accessed the fewest times, and of those, the ones that were accessed longest ago.
+    LeastFrequentlyUsed,
+}
+
+/// Limits the size of a directory of file-backed values, by evicting entries when a value is written.
+///
+/// Values are attached to a store through [`FileBackedValueBuilder::store`](crate::FileBackedValueBuilder::store).
+/// Accesses are recorded in an index file in the directory, rather than relying on the access time of files,
+/// which many file systems do not keep up to date. Files that are not attached to the store are evicted as well,
+/// as if they were last accessed when they were last modified.
+///
+/// To keep reads cheap, accesses are collected in memory, and only written to the index when a value is written,
+/// when entries are evicted, on the first access 30 seconds or more after they were last written, through
+/// [`flush`](Self::flush), or when the last clone of the store is dropped, including those held by its values.
+/// Clones of a store share the accesses that are collected. Stores that are never dropped, such as those in statics,
+/// should be flushed before the process exits.
+#[derive(Clone, Debug)]
+pub struct FileBackedStore {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    max_entries: Option<usize>,
+    eviction: EvictionPolicy,
+    pending: Arc<Mutex<Pending>>,
+}
+
+/// Total size of the entries of a [`FileBackedStore`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoreUsage {
+    pub bytes: u64,
+    pub entries: usize,
+}
+
+/// Accesses of the entries of a store, by their path relative to the directory of the store.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    entries: BTreeMap<String, Access>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Access {
+    /// Time of the last access, in milliseconds since the Unix epoch.
+    accessed: u64,
+    accesses: u64,
+}
+
+/// Accesses that have not been written to the index in `dir` yet, which are written when this is dropped.
+#[derive(Debug)]
+struct Pending {
+    dir: PathBuf,
+    index: Index,
+    flushed: Instant,
+}
+
+/// A file in the directory of a store.
+struct StoreEntry {
+    key: String,
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+impl FileBackedStore {
+    /// Manage the file-backed values in `dir` and its subdirectories, without any limits.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        Self {
+            pending: Arc::new(Mutex::new(Pending { dir: dir.clone(), index: Index::default(), flushed: Instant::now() })),
+            dir,
+            max_bytes: None,
+            max_entries: None,
+            eviction: EvictionPolicy::default(),
+        }
+    }
+
+    /// Evict entries when their total size exceeds `max_bytes`.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Evict entries when there are more than `max_entries`.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Set which entries are evicted first.
+    pub fn eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    /// Directory containing the entries of the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Current total size of the entries.
+    pub fn usage(&self) -> FileBackedValueResult<StoreUsage> {
+        let entries = self.entries()?;
+        Ok(StoreUsage {
+            bytes: entries.iter().map(|entry| entry.len).sum(),
+            entries: entries.len(),
+        })
+    }
+
+    /// Evict entries until the store is within its limits.
+    /// This happens automatically whenever a value that is attached to the store is written.
+    pub fn evict(&self) -> FileBackedValueResult<()> {
+        self.update(|index| self.evict_except(index, None))
+    }
+
+    /// Write the accesses that were collected in memory to the index.
+    pub fn flush(&self) -> FileBackedValueResult<()> {
+        if self.pending.lock().unwrap_or_else(PoisonError::into_inner).index.entries.is_empty() {
+            return Ok(());
+        }
+        self.update(|_| Ok(()))
+    }
+
+    /// Record that the entry at `path` was read.
+    /// The access is only written to the index once the pending accesses are flushed.
+    pub(crate) fn record_access(&self, path: &Path) -> FileBackedValueResult<()> {
+        let Some(key) = self.key(path) else {
+            return Ok(());
+        };
+
+        let flush = {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            pending.index.record(key);
+            pending.flushed.elapsed() >= FLUSH_INTERVAL
+        };
+        if flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Record that the entry at `path` was written, and evict other entries if the store exceeds its limits.
+    pub(crate) fn record_write(&self, path: &Path) -> FileBackedValueResult<()> {
+        let Some(key) = self.key(path) else {
+            return Ok(());
+        };
+
+        self.update(|index| {
+            index.record(key.clone());
+            // The entry that was just written is never evicted, or it would be lost immediately under LFU.
+            self.evict_except(index, Some(&key))
+        })
+    }
+
+    /// Read the index, add the pending accesses to it, apply `f` to it, and write it back,
+    /// while holding an exclusive lock on it.
+    fn update<F>(&self, f: F) -> FileBackedValueResult<()>
+    where
+        F: FnOnce(&mut Index) -> FileBackedValueResult<()>
+    {
+        update_index(&self.dir, || {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            pending.flushed = Instant::now();
+            std::mem::take(&mut pending.index)
+        }, f)
+    }
+
+    /// Evict entries other than `keep` until the store is within its limits, and forget entries that no longer exist.
+    fn evict_except(&self, index: &mut Index, keep: Option<&str>) -> FileBackedValueResult<()> {
+        let mut entries = self.entries()?;
+        let keys: HashSet<&str> = entries.iter().map(|entry| entry.key.as_str()).collect();
+        index.entries.retain(|key, _| keys.contains(key.as_str()));
+
+        let mut bytes: u64 = entries.iter().map(|entry| entry.len).sum();
+        let mut count = entries.len();
+        let exceeds = |bytes: u64, count: usize| {
+            self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+                || self.max_entries.is_some_and(|max_entries| count > max_entries)
+        };
+        if !exceeds(bytes, count) {
+            return Ok(());
+        }
+
+        // Entries that were never recorded are treated as if they were accessed once, when they were last modified.
+        let access = |entry: &StoreEntry| index.entries.get(&entry.key).copied()
+            .unwrap_or(Access { accessed: to_millis(entry.modified), accesses: 1 });
+        match self.eviction {
+            EvictionPolicy::LeastRecentlyUsed => entries.sort_by_key(|entry| access(entry).accessed),
+            EvictionPolicy::LeastFrequentlyUsed => entries.sort_by_key(|entry| {
+                let access = access(entry);
+                (access.accesses, access.accessed)
+            }),
+        }
+
+        for entry in entries {
+            if !exceeds(bytes, count) {
+                break;
+            }
+            if keep == Some(entry.key.as_str()) {
+                continue;
+            }
+
+            match fs::remove_file(&entry.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            index.entries.remove(&entry.key);
+            bytes -= entry.len;
+            count -= 1;
+        }
+
+        Ok(())
+    }
+
+    /// All entries in the directory of the store and its subdirectories, skipping hidden files and directories.
+    fn entries(&self) -> io::Result<Vec<StoreEntry>> {
+        let mut entries = Vec::new();
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let dir_entries = match fs::read_dir(&dir) {
+                Ok(dir_entries) => dir_entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            for dir_entry in dir_entries {
+                let dir_entry = dir_entry?;
+                if dir_entry.file_name().as_encoded_bytes().starts_with(b".") {
+                    continue;
+                }
+
+                let path = dir_entry.path();
+                let metadata = dir_entry.metadata()?;
+                if metadata.is_dir() {
+                    dirs.push(path);
+                } else if let Some(key) = self.key(&path) {
+                    entries.push(StoreEntry {
+                        key,
+                        path,
+                        len: metadata.len(),
+                        modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Key of the entry at `path` in the index, or None if it is not in the directory of the store.
+    fn key(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(&self.dir).ok()
+            .map(|relative| relative.to_string_lossy().into_owned())
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if !self.index.entries.is_empty() {
+            let pending = std::mem::take(&mut self.index);
+            // Access times only decide what is evicted first, which is not worth panicking over.
+            let _ = update_index(&self.dir, || pending, |_| Ok(()));
+        }
+    }
+}
+
+/// Read the index in `dir`, add the accesses returned by `pending` to it, apply `f` to it, and write it back,
+/// while holding an exclusive lock on it.
+fn update_index<P, F>(dir: &Path, pending: P, f: F) -> FileBackedValueResult<()>
+where
+    P: FnOnce() -> Index,
+    F: FnOnce(&mut Index) -> FileBackedValueResult<()>
+{
+    let index_path = dir.join(INDEX_FILENAME);
+    let _lock = FileLock::exclusive(&index_path)?;
+    // An unreadable index only loses access history, which is not worth failing over.
+    let mut index: Index = read_raw(&index_path)?
+        .and_then(|raw| serde_json::from_slice(&raw.bytes).ok())
+        .unwrap_or_default();
+    index.merge(pending());
+
+    f(&mut index)?;
+    write_bytes(&index_path, &serde_json::to_vec(&index)?)
+}
+
+impl Index {
+    /// Record an access of the entry with the given key.
+    fn record(&mut self, key: String) {
+        let accessed = to_millis(SystemTime::now());
+        self.entries.entry(key)
+            .and_modify(|access| {
+                access.accessed = accessed;
+                access.accesses = access.accesses.saturating_add(1);
+            })
+            .or_insert(Access { accessed, accesses: 1 });
+    }
+
+    /// Add the accesses recorded in `other`, which happened after those in this index.
+    fn merge(&mut self, other: Index) {
+        for (key, access) in other.entries {
+            self.entries.entry(key)
+                .and_modify(|existing| {
+                    existing.accessed = existing.accessed.max(access.accessed);
+                    existing.accesses = existing.accesses.saturating_add(access.accesses);
+                })
+                .or_insert(access);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::{convert::Infallible, fs, io, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::Stored, ExpiryPolicy, FileBackedValue, FileBackedValueResult, Format, Json, LockPolicy, TryInsertError};
+
+/// A file-backed value of a fixed type `T`, which keeps the deserialized value in memory.
+///
+/// The backing file is only read again when its modification time or size changes,
+/// so repeated accesses do not need to parse the file every time.
+#[derive(Clone, Debug)]
+pub struct TypedFileBackedValue<T, Fmt = Json> {
+    inner: FileBackedValue<Fmt>,
+    cache: Option<Cached<T>>,
+}
+
+#[derive(Clone, Debug)]
+struct Cached<T> {
+    stored: Stored<T>,
+    stamp: Option<FileStamp>,
+}
+
+/// Cheap fingerprint of the backing file, used to detect changes made by others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl<T> TypedFileBackedValue<T>
+where
+    T: DeserializeOwned + Serialize
+{
+    /// Create a typed file-backed value in the user's data directory.
+    ///
+    /// ### Panics
+    /// If no valid home directory is found. See [`TypedFileBackedValue::try_new`] for a non-panicking version.
+    pub fn new(filename: &str) -> Self {
+        FileBackedValue::new(filename).into()
+    }
+
+    /// Create a typed file-backed value in the user's data directory,
+    /// or return an error if no valid home directory is found.
+    pub fn try_new(filename: &str) -> FileBackedValueResult<Self> {
+        FileBackedValue::try_new(filename).map(Into::into)
+    }
+
+    /// Create a typed file-backed value in the specified directory.
+    pub fn new_at(filename: &str, parent: &Path) -> Self {
+        FileBackedValue::new_at(filename, parent).into()
+    }
+}
+
+impl<T, Fmt> TypedFileBackedValue<T, Fmt>
+where
+    T: DeserializeOwned + Serialize,
+    Fmt: Format,
+{
+    /// Set the duration after which the file is considered dirty and needs to be recomputed.
+    pub fn set_dirty_time(&mut self, dirty_time: Duration) {
+        self.inner.set_dirty_time(dirty_time);
+    }
+
+    /// Set the policy that decides when the file is considered dirty and needs to be recomputed.
+    pub fn set_expiry(&mut self, policy: impl ExpiryPolicy + 'static) {
+        self.inner.set_expiry(policy);
+    }
+
+    /// Set when to take advisory locks on the backing file, to coordinate access between processes.
+    pub fn set_lock_policy(&mut self, lock_policy: LockPolicy) {
+        self.inner.set_lock_policy(lock_policy);
+    }
+
+    /// Path to the backing file.
+    pub fn path(&self) -> &PathBuf {
+        self.inner.path()
+    }
+
+    /// Clear the cached value and remove the backing file.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.cache = None;
+        self.inner.clear()
+    }
+
+    /// Get the current value, which might be None if the backing file does not yet exist.
+    pub fn get(&mut self) -> FileBackedValueResult<Option<&T>> {
+        // Take the stamp before reading, so that a concurrent write results in a re-read next time.
+        let Some(stamp) = FileStamp::of(self.path()) else {
+            self.cache = None;
+            return Ok(None);
+        };
+
+        if self.cache.as_ref().is_none_or(|cached| cached.stamp != Some(stamp)) {
+            self.cache = self.inner.read()?
+                .map(|stored| Cached { stored, stamp: Some(stamp) });
+        }
+
+        // The cached value might have expired since it was read.
+        let value = self.cache.as_ref()
+            .filter(|cached| !self.inner.options.is_expired(&cached.stored.header))
+            .map(|cached| &cached.stored.value);
+        if value.is_some() {
+            self.inner.record_access();
+        }
+        Ok(value)
+    }
+
+    /// Get the current value, or insert `default` if the backing file does not exist or is dirty.
+    pub fn get_or_insert(&mut self, default: T) -> FileBackedValueResult<&T> {
+        self.get_or_insert_with(|| default)
+    }
+
+    /// Get the current value, or insert `default()` if the backing file does not exist or is dirty.
+    pub fn get_or_insert_with<F>(&mut self, default: F) -> FileBackedValueResult<&T>
+    where
+        F: FnOnce() -> T,
+    {
+        self.get_or_try_insert_with(|| Ok::<_, Infallible>(default()))
+            .map_err(TryInsertError::into_storage)
+    }
+
+    /// Get the current value, or insert the result of `default()` if the backing file does not exist or is dirty.
+    /// If `default()` returns an error, nothing is written and the error is returned.
+    pub fn get_or_try_insert_with<F, E>(&mut self, default: F) -> Result<&T, TryInsertError<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if self.get()?.is_none() {
+            let stored = self.inner.get_or_try_insert_stored_with(default)?;
+            let stamp = FileStamp::of(self.path());
+            self.cache = Some(Cached { stored, stamp });
+        }
+
+        Ok(&self.cache.as_ref().expect("value was just inserted").stored.value)
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, and caches it.
+    pub fn insert(&mut self, value: T) -> FileBackedValueResult<()> {
+        self.write(value, None)
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, caches it, and lets it expire after `ttl`.
+    /// A `ttl` too large to be represented does not expire the value.
+    pub fn insert_with_ttl(&mut self, value: T, ttl: Duration) -> FileBackedValueResult<()> {
+        self.write(value, SystemTime::now().checked_add(ttl))
+    }
+
+    /// Writes `value` to the backing file, replacing any existing value, caches it, and lets it expire at `expires`.
+    pub fn insert_until(&mut self, value: T, expires: SystemTime) -> FileBackedValueResult<()> {
+        self.write(value, Some(expires))
+    }
+
+    /// Consume the typed value, returning the underlying untyped file-backed value.
+    pub fn into_inner(self) -> FileBackedValue<Fmt> {
+        self.inner
+    }
+
+    /// Write `value` to the backing file and cache it, along with the metadata that was written with it.
+    fn write(&mut self, value: T, expires: Option<SystemTime>) -> FileBackedValueResult<()> {
+        self.cache = None;
+        let header = self.inner.write(&value, expires)?;
+        let stamp = FileStamp::of(self.path());
+        self.cache = Some(Cached { stored: Stored { header, value }, stamp });
+        Ok(())
+    }
+}
+
+impl<T, Fmt> From<FileBackedValue<Fmt>> for TypedFileBackedValue<T, Fmt> {
+    fn from(inner: FileBackedValue<Fmt>) -> Self {
+        Self { inner, cache: None }
+    }
+}
+
+impl FileStamp {
+    /// Fingerprint of the file at `path`, or None if it does not exist.
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "async")]
+
+use std::{sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread, time::Duration};
+
+use file_backed_value::{FileBackedValue, LockPolicy};
+
+#[tokio::test]
+async fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    assert_eq!(value.get_async::<String>().await.unwrap(), None);
+    value.insert_async(&"inserted").await.unwrap();
+    assert_eq!(value.get_async::<String>().await.unwrap().as_deref(), Some("inserted"));
+    // Values written asynchronously can be read by the blocking accessors, and vice versa.
+    assert_eq!(value.get::<String>().unwrap().as_deref(), Some("inserted"));
+    value.insert(&"blocking").unwrap();
+    assert_eq!(value.get_async::<String>().await.unwrap().as_deref(), Some("blocking"));
+}
+
+#[tokio::test]
+async fn missing_value_is_computed_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    assert_eq!(value.get_or_insert_with_async(|| async { 1u32 }).await.unwrap(), 1);
+    assert_eq!(value.get_or_insert_with_async(|| async { 2u32 }).await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_computations_are_coalesced() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = Arc::new(FileBackedValue::builder("value").dir(dir.path()).build().unwrap());
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let tasks: Vec<_> = (0..8).map(|_| {
+        let value = Arc::clone(&value);
+        let computations = Arc::clone(&computations);
+        tokio::spawn(async move {
+            value.get_or_insert_with_async(|| async move {
+                computations.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                42u32
+            }).await.unwrap()
+        })
+    }).collect();
+
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 42);
+    }
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn threads_and_tasks_are_coordinated_by_the_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = || FileBackedValue::builder("value").dir(dir.path())
+        .lock_policy(LockPolicy::HoldDuringCompute)
+        .build().unwrap();
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let blocking = build();
+    let thread_computations = Arc::clone(&computations);
+    let thread = thread::spawn(move || blocking.get_or_insert_with(|| {
+        thread_computations.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(100));
+        1u32
+    }).unwrap());
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let res = build().get_or_insert_with_async(|| async {
+        computations.fetch_add(1, Ordering::SeqCst);
+        2u32
+    }).await.unwrap();
+
+    // Whichever computed the value first, the other one reads it.
+    assert_eq!(thread.join().unwrap(), res);
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
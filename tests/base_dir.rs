@@ -0,0 +1,47 @@
+use std::env;
+
+use file_backed_value::{BaseDir, FileBackedValue, Project, DIR_ENV_VAR};
+
+const APPLICATION: &str = "file-backed-value-test";
+
+fn path(base_dir: BaseDir, project: Option<Project>) -> Option<std::path::PathBuf> {
+    // Values are redirected to the environment variable instead, which is covered by the tests in `location.rs`.
+    if env::var_os(DIR_ENV_VAR).is_some() {
+        return None;
+    }
+
+    let builder = FileBackedValue::builder("value").base_dir(base_dir);
+    let builder = match project {
+        Some(project) => builder.project(project),
+        None => builder,
+    };
+    Some(builder.build().unwrap().path().clone())
+}
+
+#[test]
+fn kinds_of_directories_are_kept_apart() {
+    let (Some(data), Some(cache), Some(config)) =
+        (path(BaseDir::Data, None), path(BaseDir::Cache, None), path(BaseDir::Config, None)) else {
+        return;
+    };
+
+    assert_ne!(data, cache);
+    assert_ne!(cache, config);
+    assert!(data.ends_with("value"));
+}
+
+#[test]
+fn project_values_are_stored_in_its_subdirectory() {
+    let project = || Some(Project::new("com", "Example", APPLICATION));
+    let (Some(cache), Some(project_cache), Some(project_config)) =
+        (path(BaseDir::Cache, None), path(BaseDir::Cache, project()), path(BaseDir::Config, project())) else {
+        return;
+    };
+
+    let in_project = |path: &std::path::Path| path.components()
+        .any(|component| component.as_os_str().to_string_lossy().contains(APPLICATION));
+    assert!(!in_project(&cache));
+    assert!(in_project(&project_cache));
+    assert!(in_project(&project_config));
+    assert_ne!(project_cache, project_config);
+}
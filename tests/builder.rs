@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use file_backed_value::{BaseDir, FileBackedStore, FileBackedValue, FileBackedValueBuilder, FileBackedValueError, Project};
+
+fn builder(dir: &tempfile::TempDir) -> FileBackedValueBuilder {
+    FileBackedValue::builder("value").dir(dir.path())
+}
+
+/// The message of the `InvalidConfig` error that building with `builder` results in.
+fn invalid(builder: FileBackedValueBuilder) -> String {
+    match builder.build() {
+        Err(FileBackedValueError::InvalidConfig(message)) => message,
+        res => panic!("expected an invalid configuration, got {res:?}"),
+    }
+}
+
+#[test]
+fn configured_value_is_built() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("some/value").dir(dir.path())
+        .dirty_time(Duration::from_millis(50))
+        .stale_if_error(Duration::from_secs(60))
+        .build().unwrap();
+
+    // Path separators are sanitized out of the filename.
+    assert_eq!(value.path().parent(), Some(dir.path()));
+    value.insert(&1u32).unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
+
+#[test]
+fn invalid_filename_is_rejected() {
+    assert!(invalid(FileBackedValue::builder("")).contains("empty after sanitization"));
+}
+
+#[test]
+fn explicit_directory_is_exclusive() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(invalid(builder(&dir).base_dir(BaseDir::Cache)).contains("explicit directory"));
+    assert!(invalid(builder(&dir).project(Project::new("com", "Example", "app"))).contains("explicit directory"));
+}
+
+#[test]
+fn expiry_must_be_positive() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(invalid(builder(&dir).dirty_time(Duration::ZERO)).contains("dirty time"));
+    assert!(invalid(builder(&dir).expiry(file_backed_value::Ttl(Duration::ZERO))).contains("expiry policy"));
+}
+
+#[test]
+fn stale_values_require_expiry() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(invalid(builder(&dir).stale_if_error(Duration::from_secs(1))).contains("stale-if-error"));
+    assert!(invalid(builder(&dir).stale_while_revalidate(Duration::from_secs(1))).contains("stale-while-revalidate"));
+}
+
+#[test]
+fn store_must_contain_the_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let other = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path());
+
+    let store_builder = || FileBackedValue::builder("value").store(store.clone());
+    assert!(invalid(store_builder().base_dir(BaseDir::Cache)).contains("store"));
+    assert!(invalid(store_builder().project(Project::new("com", "Example", "app"))).contains("store"));
+    assert!(invalid(store_builder().dir(other.path())).contains("not inside the directory of the store"));
+
+    assert_eq!(store_builder().build().unwrap().path(), &dir.path().join("value"));
+    assert!(store_builder().dir(dir.path().join("nested")).build().is_ok());
+}
+
+#[test]
+fn migrations_must_be_older_than_the_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let res = invalid(builder(&dir).schema_version(1).migration(1, |value| value));
+    assert!(res.contains("not older than the schema version"));
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use file_backed_value::{Checksum, FileBackedValue, FileBackedValueError};
+
+fn value(dir: &tempfile::TempDir, checksum: Checksum) -> FileBackedValue {
+    FileBackedValue::builder("value").dir(dir.path()).checksum(checksum).build().unwrap()
+}
+
+/// Flip a bit of the byte at `index` of the backing file.
+fn flip(value: &FileBackedValue, index: usize) {
+    let mut bytes = fs::read(value.path()).unwrap();
+    bytes[index] ^= 1;
+    fs::write(value.path(), bytes).unwrap();
+}
+
+#[test]
+fn round_trip() {
+    for checksum in [Checksum::Crc32c, Checksum::Blake3] {
+        let dir = tempfile::tempdir().unwrap();
+        let value = value(&dir, checksum);
+        value.insert(&"value").unwrap();
+        assert_eq!(value.get::<String>().unwrap().as_deref(), Some("value"));
+    }
+}
+
+#[test]
+fn tampered_payload_is_detected() {
+    for checksum in [Checksum::Crc32c, Checksum::Blake3] {
+        let dir = tempfile::tempdir().unwrap();
+        let value = value(&dir, checksum);
+        value.insert(&12345u32).unwrap();
+        // Flipping the lowest bit of a digit turns it into another digit, so the value still deserializes.
+        let bytes = fs::read(value.path()).unwrap();
+        flip(&value, bytes.windows(5).position(|window| window == b"12345").unwrap() + 1);
+        assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::ChecksumMismatch)), "{checksum:?}");
+    }
+}
+
+#[test]
+fn tampered_frame_is_detected() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, Checksum::Crc32c);
+    value.insert(&1u32).unwrap();
+    // The algorithm, and then the checksum itself.
+    for index in [4, 5] {
+        flip(&value, index);
+        assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::ChecksumMismatch)), "{index}");
+        flip(&value, index);
+    }
+
+    // Without the magic bytes, the file is no longer recognized as framed, and is dirty like any other file without one.
+    flip(&value, 0);
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
+
+#[test]
+fn files_without_a_checksum_are_dirty() {
+    let dir = tempfile::tempdir().unwrap();
+    FileBackedValue::builder("value").dir(dir.path()).build().unwrap().insert(&1u32).unwrap();
+
+    let value = value(&dir, Checksum::Blake3);
+    assert_eq!(value.get::<u32>().unwrap(), None);
+    assert_eq!(value.get_or_insert(2u32).unwrap(), 2);
+    assert_eq!(value.get::<u32>().unwrap(), Some(2));
+    assert!(fs::read(value.path()).unwrap().starts_with(b"FBVC"));
+}
+
+#[test]
+fn framed_files_are_verified_without_a_checksum_configured() {
+    let dir = tempfile::tempdir().unwrap();
+    let framed = value(&dir, Checksum::Crc32c);
+    framed.insert(&1u32).unwrap();
+
+    let plain = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+    assert_eq!(plain.get::<u32>().unwrap(), Some(1));
+    let len = fs::read(plain.path()).unwrap().len();
+    flip(&plain, len - 2);
+    assert!(matches!(plain.get::<u32>(), Err(FileBackedValueError::ChecksumMismatch)));
+}
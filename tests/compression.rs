@@ -0,0 +1,82 @@
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "lz4"))]
+use file_backed_value::Compression;
+use file_backed_value::{FileBackedValue, FileBackedValueError};
+
+/// Every algorithm that is enabled, along with the magic bytes that its files start with.
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "lz4"))]
+fn algorithms() -> Vec<(Compression, &'static [u8])> {
+    vec![
+        #[cfg(feature = "zstd")]
+        (Compression::Zstd, &[0x28, 0xb5, 0x2f, 0xfd]),
+        #[cfg(feature = "gzip")]
+        (Compression::Gzip, &[0x1f, 0x8b]),
+        #[cfg(feature = "lz4")]
+        (Compression::Lz4, &[0x04, 0x22, 0x4d, 0x18]),
+    ]
+}
+
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "lz4"))]
+#[test]
+fn round_trip() {
+    for (compression, magic) in algorithms() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = FileBackedValue::builder("value").dir(dir.path()).compression(compression).build().unwrap();
+        let text = "compressible ".repeat(100);
+
+        value.insert(&text).unwrap();
+        let bytes = std::fs::read(value.path()).unwrap();
+        assert!(bytes.starts_with(magic), "{compression:?}");
+        assert!(bytes.len() < text.len(), "{compression:?}");
+        assert_eq!(value.get::<String>().unwrap(), Some(text), "{compression:?}");
+    }
+}
+
+#[cfg(any(feature = "zstd", feature = "gzip", feature = "lz4"))]
+#[test]
+fn uncompressed_files_are_still_read() {
+    for (compression, magic) in algorithms() {
+        let dir = tempfile::tempdir().unwrap();
+        FileBackedValue::builder("value").dir(dir.path()).build().unwrap().insert(&1u32).unwrap();
+
+        let mut value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+        value.set_compression(compression);
+        assert_eq!(value.get::<u32>().unwrap(), Some(1), "{compression:?}");
+
+        value.insert(&2u32).unwrap();
+        assert!(std::fs::read(value.path()).unwrap().starts_with(magic), "{compression:?}");
+        assert_eq!(value.get::<u32>().unwrap(), Some(2), "{compression:?}");
+    }
+}
+
+#[cfg(all(feature = "zstd", feature = "gzip"))]
+#[test]
+fn files_compressed_with_another_algorithm_are_read() {
+    let dir = tempfile::tempdir().unwrap();
+    FileBackedValue::builder("value").dir(dir.path()).compression(Compression::Gzip).build().unwrap()
+        .insert(&1u32).unwrap();
+
+    let value = FileBackedValue::builder("value").dir(dir.path()).compression(Compression::Zstd).build().unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+}
+
+#[test]
+fn algorithms_without_their_feature_are_reported() {
+    let disabled: &[(&str, &[u8])] = &[
+        #[cfg(not(feature = "zstd"))]
+        ("zstd", &[0x28, 0xb5, 0x2f, 0xfd, 0]),
+        #[cfg(not(feature = "gzip"))]
+        ("gzip", &[0x1f, 0x8b, 0]),
+        #[cfg(not(feature = "lz4"))]
+        ("lz4", &[0x04, 0x22, 0x4d, 0x18, 0]),
+    ];
+
+    for &(name, bytes) in disabled {
+        let dir = tempfile::tempdir().unwrap();
+        let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+        std::fs::write(value.path(), bytes).unwrap();
+        match value.get::<u32>() {
+            Err(FileBackedValueError::UnsupportedCompression(unsupported)) => assert_eq!(unsupported, name),
+            res => panic!("{name}: {res:?}"),
+        }
+    }
+}
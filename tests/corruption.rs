@@ -0,0 +1,77 @@
+use std::fs;
+
+use file_backed_value::{CorruptionPolicy, FileBackedValue, FileBackedValueError, LockPolicy};
+
+fn corrupt_value(dir: &tempfile::TempDir, policy: CorruptionPolicy) -> FileBackedValue {
+    let value = FileBackedValue::builder("value").dir(dir.path()).corruption_policy(policy).build().unwrap();
+    fs::write(value.path(), "not a value").unwrap();
+    value
+}
+
+fn quarantined(dir: &tempfile::TempDir) -> Vec<String> {
+    fs::read_dir(dir.path()).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("value.corrupt-"))
+        .collect()
+}
+
+#[test]
+fn error_policy_returns_the_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = corrupt_value(&dir, CorruptionPolicy::Error);
+    assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::JsonError(_))));
+    assert!(value.get_or_insert(1u32).is_err());
+    assert_eq!(fs::read_to_string(value.path()).unwrap(), "not a value");
+}
+
+#[test]
+fn recompute_policy_overwrites_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = corrupt_value(&dir, CorruptionPolicy::Recompute);
+    assert_eq!(value.get::<u32>().unwrap(), None);
+    assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+    assert!(quarantined(&dir).is_empty());
+}
+
+#[test]
+fn quarantine_policy_moves_the_file_aside() {
+    for lock_policy in [LockPolicy::Disabled, LockPolicy::PerOperation, LockPolicy::HoldDuringCompute] {
+        let dir = tempfile::tempdir().unwrap();
+        let mut value = corrupt_value(&dir, CorruptionPolicy::Quarantine);
+        value.set_lock_policy(lock_policy);
+
+        assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+        assert_eq!(value.get::<u32>().unwrap(), Some(1));
+        let quarantined = quarantined(&dir);
+        assert_eq!(quarantined.len(), 1, "{lock_policy:?}");
+        assert_eq!(fs::read_to_string(dir.path().join(&quarantined[0])).unwrap(), "not a value");
+    }
+}
+
+#[test]
+fn encrypted_files_are_not_handled_by_the_policy() {
+    for policy in [CorruptionPolicy::Recompute, CorruptionPolicy::Quarantine] {
+        let dir = tempfile::tempdir().unwrap();
+        let value = FileBackedValue::builder("value").dir(dir.path()).corruption_policy(policy).build().unwrap();
+        // An encrypted file, which cannot be read without a key, whether or not the feature is enabled.
+        fs::write(value.path(), b"FBVE\x01ciphertext").unwrap();
+
+        assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::DecryptionFailed)));
+        assert!(matches!(value.get_or_insert(1u32), Err(FileBackedValueError::DecryptionFailed)));
+        assert_eq!(fs::read(value.path()).unwrap(), b"FBVE\x01ciphertext");
+        assert!(quarantined(&dir).is_empty());
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn quarantine_policy_moves_the_file_aside_async() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut value = corrupt_value(&dir, CorruptionPolicy::Quarantine);
+    value.set_lock_policy(LockPolicy::PerOperation);
+
+    assert_eq!(value.get_async::<u32>().await.unwrap(), None);
+    assert!(!value.path().exists());
+    assert_eq!(quarantined(&dir).len(), 1);
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "encryption")]
+
+use std::{error::Error, fs};
+
+use file_backed_value::{CorruptionPolicy, FileBackedValue, FileBackedValueBuilder, FileBackedValueError, KeyProvider};
+
+const KEY: [u8; 32] = [7; 32];
+
+fn builder(dir: &tempfile::TempDir, name: &str) -> FileBackedValueBuilder {
+    FileBackedValue::builder(name).dir(dir.path())
+}
+
+#[test]
+fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = builder(&dir, "value").encryption(KEY).build().unwrap();
+    value.insert(&"secret").unwrap();
+
+    assert!(!fs::read(value.path()).unwrap().windows(6).any(|window| window == b"secret"));
+    assert_eq!(value.get::<String>().unwrap().as_deref(), Some("secret"));
+}
+
+#[test]
+fn wrong_key_fails_without_overwriting() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir, "value").encryption(KEY).build().unwrap().insert(&1u32).unwrap();
+
+    let value = builder(&dir, "value").encryption([8; 32]).corruption_policy(CorruptionPolicy::Recompute).build().unwrap();
+    assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::DecryptionFailed)));
+    assert!(matches!(value.get_or_insert(2u32), Err(FileBackedValueError::DecryptionFailed)));
+
+    let value = builder(&dir, "value").encryption(KEY).build().unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+}
+
+#[test]
+fn encrypted_file_cannot_be_read_without_a_key() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir, "value").encryption(KEY).build().unwrap().insert(&1u32).unwrap();
+
+    let value = builder(&dir, "value").build().unwrap();
+    assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::DecryptionFailed)));
+}
+
+#[test]
+fn plain_file_is_dirty_and_overwritten_encrypted() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir, "value").build().unwrap().insert(&"plaintext").unwrap();
+
+    let value = builder(&dir, "value").encryption(KEY).build().unwrap();
+    assert_eq!(value.get::<String>().unwrap(), None);
+    assert_eq!(value.get_or_insert_with(|| "secret".to_owned()).unwrap(), "secret");
+    assert!(!fs::read(value.path()).unwrap().windows(9).any(|window| window == b"plaintext"));
+    assert_eq!(value.get::<String>().unwrap().as_deref(), Some("secret"));
+}
+
+#[test]
+fn tampered_file_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = builder(&dir, "value").encryption(KEY).build().unwrap();
+    value.insert(&1u32).unwrap();
+
+    let mut bytes = fs::read(value.path()).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    fs::write(value.path(), bytes).unwrap();
+    assert!(matches!(value.get::<u32>(), Err(FileBackedValueError::DecryptionFailed)));
+}
+
+#[test]
+fn files_cannot_be_swapped() {
+    let dir = tempfile::tempdir().unwrap();
+    let admin = builder(&dir, "admin").encryption(KEY).build().unwrap();
+    let guest = builder(&dir, "guest").encryption(KEY).build().unwrap();
+    admin.insert(&true).unwrap();
+    guest.insert(&false).unwrap();
+
+    fs::copy(admin.path(), guest.path()).unwrap();
+    assert!(matches!(guest.get::<bool>(), Err(FileBackedValueError::DecryptionFailed)));
+}
+
+#[test]
+fn unavailable_key_is_reported() {
+    struct Unavailable;
+
+    impl KeyProvider for Unavailable {
+        fn key(&self) -> Result<[u8; 32], Box<dyn Error + Send + Sync>> {
+            Err("keyring is locked".into())
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let value = builder(&dir, "value").encryption(Unavailable).build().unwrap();
+    let e = value.insert(&1u32).unwrap_err();
+    assert!(matches!(e, FileBackedValueError::KeyUnavailable(_)));
+    assert_eq!(e.source().unwrap().to_string(), "keyring is locked");
+}
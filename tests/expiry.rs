@@ -0,0 +1,84 @@
+use std::{thread, time::Duration};
+
+use file_backed_value::{AllOf, AnyOf, Every, FileBackedValue, FileBackedValueError, SourcesChanged, Ttl, TypedFileBackedValue};
+
+#[test]
+fn value_expires_after_the_dirty_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(Duration::from_millis(50))
+        .build().unwrap();
+
+    assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(value.get::<u32>().unwrap(), None);
+    assert_eq!(value.get_or_insert(2u32).unwrap(), 2);
+}
+
+#[test]
+fn unrepresentable_dirty_time_never_expires() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(Duration::MAX)
+        .build().unwrap();
+
+    assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+    assert_eq!(value.get_or_insert(2u32).unwrap(), 1);
+}
+
+#[test]
+fn value_expires_after_its_ttl() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    value.insert_with_ttl(&1u32, Duration::from_millis(50)).unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
+
+#[test]
+fn unrepresentable_ttl_never_expires() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    value.insert_with_ttl(&1u32, Duration::MAX).unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+}
+
+#[test]
+fn typed_value_with_unrepresentable_ttl_never_expires() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut value = TypedFileBackedValue::<u32>::new_at("value", dir.path());
+
+    value.insert_with_ttl(1, Duration::MAX).unwrap();
+    assert_eq!(value.get().unwrap(), Some(&1));
+}
+
+#[test]
+fn zero_periods_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = |policy| FileBackedValue::builder("value").dir(dir.path()).expiry(policy).build();
+
+    assert!(matches!(build(AnyOf::new().with(Every(Duration::ZERO))), Err(FileBackedValueError::InvalidConfig(_))));
+    assert!(matches!(build(AnyOf::new().with(Ttl(Duration::ZERO))), Err(FileBackedValueError::InvalidConfig(_))));
+    assert!(matches!(build(AnyOf::new().with(AllOf::new().with(Every(Duration::from_micros(1))))), Err(FileBackedValueError::InvalidConfig(_))));
+    assert!(build(AnyOf::new().with(Every(Duration::from_secs(3600)))).is_ok());
+}
+
+#[test]
+fn missing_source_does_not_expire_the_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source.txt");
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .expiry(SourcesChanged::new([&source]))
+        .build().unwrap();
+
+    assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+
+    thread::sleep(Duration::from_millis(10));
+    std::fs::write(&source, "changed").unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
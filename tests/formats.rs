@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use file_backed_value::{FileBackedValue, Format, Json, PrettyJson};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    name: String,
+    retries: u32,
+    tags: Vec<String>,
+}
+
+fn settings() -> Settings {
+    Settings { name: "example".to_owned(), retries: 3, tags: vec!["a".to_owned(), "b".to_owned()] }
+}
+
+/// Write and read back a value in `format`, with a dirty time such that its expiry is stored as well.
+fn round_trip<Fmt: Format>(format: Fmt) {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(Duration::from_secs(60))
+        .format(format)
+        .build().unwrap();
+
+    assert_eq!(value.get::<Settings>().unwrap(), None);
+    value.insert(&settings()).unwrap();
+    assert_eq!(value.get::<Settings>().unwrap(), Some(settings()));
+}
+
+#[test]
+fn json() {
+    round_trip(Json);
+    round_trip(PrettyJson);
+}
+
+#[test]
+fn pretty_json_is_indented() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::new_at("value", dir.path()).with_format(PrettyJson);
+    value.insert(&settings()).unwrap();
+    assert!(std::fs::read_to_string(value.path()).unwrap().contains("\n  "));
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode() {
+    round_trip(file_backed_value::Bincode);
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn postcard() {
+    round_trip(file_backed_value::Postcard);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor() {
+    round_trip(file_backed_value::Cbor);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack() {
+    round_trip(file_backed_value::MessagePack);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml() {
+    round_trip(file_backed_value::Toml);
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn ron() {
+    round_trip(file_backed_value::Ron);
+}
@@ -0,0 +1,46 @@
+use std::fs;
+
+use file_backed_value::FileBackedValue;
+
+#[test]
+fn value_is_dirty_when_an_input_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, "one").unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).inputs([&input]).build().unwrap();
+
+    assert_eq!(value.get_or_insert(1u32).unwrap(), 1);
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+
+    fs::write(&input, "three").unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), None);
+    assert_eq!(value.get_or_insert(2u32).unwrap(), 2);
+    assert_eq!(value.get::<u32>().unwrap(), Some(2));
+}
+
+#[test]
+fn value_is_dirty_when_an_input_is_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, "one").unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).inputs([&input]).build().unwrap();
+
+    value.insert(&1u32).unwrap();
+    fs::remove_file(&input).unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_value_is_dirty_when_an_input_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, "one").unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).inputs([&input]).build().unwrap();
+
+    assert_eq!(value.get_or_insert_with_async(|| async { 1u32 }).await.unwrap(), 1);
+    assert_eq!(value.get_async::<u32>().await.unwrap(), Some(1));
+
+    fs::write(&input, "three").unwrap();
+    assert_eq!(value.get_async::<u32>().await.unwrap(), None);
+}
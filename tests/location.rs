@@ -0,0 +1,67 @@
+use std::{env, sync::LazyLock};
+
+use file_backed_value::{BaseDir, FileBackedMap, FileBackedValue, Project, TypedFileBackedValue, DIR_ENV_VAR};
+use tempfile::TempDir;
+
+/// Directory to which the environment variable redirects values.
+///
+/// Every test in this binary forces it before anything else, so the variable is set while the other tests
+/// are blocked on its initialization, rather than while they read the environment.
+static DIR: LazyLock<TempDir> = LazyLock::new(|| {
+    let dir = tempfile::tempdir().unwrap();
+    // SAFETY: no other thread of this binary reads the environment until the variable is set, see above.
+    unsafe { env::set_var(DIR_ENV_VAR, dir.path()) };
+    dir
+});
+
+fn value(base_dir: BaseDir, project: Option<Project>) -> FileBackedValue {
+    let builder = FileBackedValue::builder("value").base_dir(base_dir);
+    match project {
+        Some(project) => builder.project(project),
+        None => builder,
+    }.build().unwrap()
+}
+
+#[test]
+fn kinds_of_directories_are_kept_apart() {
+    let dir = LazyLock::force(&DIR).path();
+
+    assert_eq!(value(BaseDir::Data, None).path(), &dir.join("data/value"));
+    assert_eq!(value(BaseDir::Cache, None).path(), &dir.join("cache/value"));
+    assert_eq!(value(BaseDir::Config, None).path(), &dir.join("config/value"));
+    assert_eq!(value(BaseDir::State, None).path(), &dir.join("state/value"));
+    assert_eq!(value(BaseDir::Runtime, None).path(), &dir.join("runtime/value"));
+
+    let cache = value(BaseDir::Cache, None);
+    let config = value(BaseDir::Config, None);
+    cache.insert(&1u32).unwrap();
+    config.insert(&2u32).unwrap();
+    assert_eq!(cache.get::<u32>().unwrap(), Some(1));
+    assert_eq!(config.get::<u32>().unwrap(), Some(2));
+}
+
+#[test]
+fn projects_are_kept_apart() {
+    let dir = LazyLock::force(&DIR).path();
+
+    let foo = value(BaseDir::Cache, Some(Project::new("com", "Example", "foo")));
+    let bar = value(BaseDir::Cache, Some(Project::new("com", "Example", "bar")));
+    assert_eq!(foo.path(), &dir.join("foo/cache/value"));
+    assert_eq!(bar.path(), &dir.join("bar/cache/value"));
+}
+
+#[test]
+fn constructors_use_the_environment_variable() {
+    let dir = LazyLock::force(&DIR).path().join("data");
+
+    let value = FileBackedValue::try_new("value").unwrap();
+    assert_eq!(value.path(), &dir.join("value"));
+    assert_eq!(FileBackedValue::new("value").path(), value.path());
+    value.insert(&1u32).unwrap();
+    assert_eq!(value.get::<u32>().unwrap(), Some(1));
+
+    let typed = TypedFileBackedValue::<u32>::try_new("typed").unwrap();
+    assert_eq!(typed.path(), &dir.join("typed"));
+    let map = FileBackedMap::<u32, u32>::try_new("map").unwrap();
+    assert_eq!(map.dir(), &dir.join("map"));
+}
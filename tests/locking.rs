@@ -0,0 +1,41 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use file_backed_value::{FileBackedValue, LockPolicy};
+
+#[test]
+fn writers_wait_for_a_computation_holding_the_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let computing = FileBackedValue::builder("value").dir(dir.path())
+        .lock_policy(LockPolicy::HoldDuringCompute)
+        .build().unwrap();
+    let writing = FileBackedValue::builder("value").dir(dir.path())
+        .lock_policy(LockPolicy::PerOperation)
+        .build().unwrap();
+
+    let (started, wait_started) = mpsc::channel();
+    let computation = thread::spawn(move || {
+        computing.get_or_insert_with(|| {
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            1u32
+        }).unwrap()
+    });
+
+    wait_started.recv().unwrap();
+    // Without the lock, this write would be overwritten by the computation that finishes afterwards.
+    writing.insert(&2u32).unwrap();
+
+    assert_eq!(computation.join().unwrap(), 1);
+    assert_eq!(writing.get::<u32>().unwrap(), Some(2));
+}
+
+#[test]
+fn stored_value_is_not_recomputed_while_holding_the_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .lock_policy(LockPolicy::HoldDuringCompute)
+        .build().unwrap();
+
+    assert_eq!(value.get_or_insert_with(|| 1u32).unwrap(), 1);
+    assert_eq!(value.get_or_insert_with(|| 2u32).unwrap(), 1);
+}
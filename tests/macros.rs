@@ -0,0 +1,60 @@
+#![cfg(feature = "macros")]
+
+use std::{env, sync::{atomic::{AtomicUsize, Ordering}, LazyLock}};
+
+use file_backed_value::{file_backed, DIR_ENV_VAR};
+use tempfile::TempDir;
+
+/// Directory in which the functions below store their results, as the macro only accepts literal directories.
+///
+/// Every test in this binary forces it before anything else, so the variable is set while the other tests
+/// are blocked on its initialization, rather than while they read the environment.
+/// Tests that do not, such as the compile tests in `macros_ui.rs`, must live in another binary.
+static DIR: LazyLock<TempDir> = LazyLock::new(|| {
+    let dir = tempfile::tempdir().unwrap();
+    // SAFETY: no other thread of this binary reads the environment until the variable is set, see above.
+    unsafe { env::set_var(DIR_ENV_VAR, dir.path()) };
+    dir
+});
+
+static SQUARES: AtomicUsize = AtomicUsize::new(0);
+
+#[file_backed]
+fn square(x: u64) -> u64 {
+    SQUARES.fetch_add(1, Ordering::SeqCst);
+    x * x
+}
+
+static GREETINGS: AtomicUsize = AtomicUsize::new(0);
+
+#[file_backed(ttl = "1h")]
+fn greet(name: &str, excited: bool) -> String {
+    GREETINGS.fetch_add(1, Ordering::SeqCst);
+    format!("Hello, {name}{}", if excited { "!" } else { "." })
+}
+
+#[test]
+fn body_runs_once_per_distinct_argument() {
+    LazyLock::force(&DIR);
+
+    assert_eq!(square(3), 9);
+    assert_eq!(square(3), 9);
+    assert_eq!(SQUARES.load(Ordering::SeqCst), 1);
+
+    assert_eq!(square(4), 16);
+    assert_eq!(square(4), 16);
+    assert_eq!(square(3), 9);
+    assert_eq!(SQUARES.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn body_runs_once_per_distinct_set_of_arguments() {
+    LazyLock::force(&DIR);
+
+    assert_eq!(greet("Alice", false), "Hello, Alice.");
+    assert_eq!(greet("Alice", true), "Hello, Alice!");
+    assert_eq!(greet("Bob", false), "Hello, Bob.");
+    assert_eq!(greet("Alice", false), "Hello, Alice.");
+    assert_eq!(greet("Alice", true), "Hello, Alice!");
+    assert_eq!(GREETINGS.load(Ordering::SeqCst), 3);
+}
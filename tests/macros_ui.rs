@@ -0,0 +1,7 @@
+#![cfg(feature = "macros")]
+
+#[test]
+fn unsupported_functions_are_rejected() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, fs, thread, time::Duration};
+
+use file_backed_value::{FileBackedMap, LockPolicy};
+
+fn map(dir: &tempfile::TempDir) -> FileBackedMap<(String, u32), String> {
+    FileBackedMap::new_at("map", dir.path())
+}
+
+fn key(name: &str, n: u32) -> (String, u32) {
+    (name.to_owned(), n)
+}
+
+#[test]
+fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let map = map(&dir);
+
+    assert_eq!(map.get(&key("a", 1)).unwrap(), None);
+    map.insert(&key("a", 1), &"one".to_owned()).unwrap();
+    map.insert(&key("a", 2), &"two".to_owned()).unwrap();
+    assert_eq!(map.get(&key("a", 1)).unwrap().as_deref(), Some("one"));
+    assert_eq!(map.get(&key("a", 2)).unwrap().as_deref(), Some("two"));
+
+    // Another map in the same directory shares the entries.
+    assert_eq!(self::map(&dir).get(&key("a", 1)).unwrap().as_deref(), Some("one"));
+}
+
+#[test]
+fn missing_entries_are_computed_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let map = map(&dir);
+
+    assert_eq!(map.get_or_insert_with(&key("a", 1), || "computed".to_owned()).unwrap(), "computed");
+    assert_eq!(map.get_or_insert_with(&key("a", 1), || unreachable!()).unwrap(), "computed");
+    assert_eq!(map.get_or_insert_with(&key("b", 1), || "other".to_owned()).unwrap(), "other");
+}
+
+#[test]
+fn removed_entries_are_missing() {
+    let dir = tempfile::tempdir().unwrap();
+    let map = map(&dir);
+
+    map.insert(&key("a", 1), &"one".to_owned()).unwrap();
+    map.remove(&key("a", 1)).unwrap();
+    assert_eq!(map.get(&key("a", 1)).unwrap(), None);
+    // Removing a missing entry is not an error.
+    map.remove(&key("a", 1)).unwrap();
+}
+
+#[test]
+fn iteration_skips_files_other_than_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut map = map(&dir);
+    assert_eq!(map.iter().unwrap().count(), 0);
+
+    map.set_lock_policy(LockPolicy::PerOperation);
+    map.insert(&key("a", 1), &"one".to_owned()).unwrap();
+    map.insert(&key("b", 2), &"two".to_owned()).unwrap();
+    for name in ["0123.tmp", "0123.corrupt-1700000000000"] {
+        fs::write(map.dir().join(name), "not an entry").unwrap();
+    }
+    assert!(fs::read_dir(map.dir()).unwrap().any(|entry| entry.unwrap().file_name().to_string_lossy().ends_with(".lock")));
+
+    let entries: BTreeMap<_, _> = map.iter().unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries, BTreeMap::from([(key("a", 1), "one".to_owned()), (key("b", 2), "two".to_owned())]));
+}
+
+#[test]
+fn entries_expire() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut map = map(&dir);
+    map.set_dirty_time(Duration::from_millis(50));
+
+    map.insert(&key("a", 1), &"old".to_owned()).unwrap();
+    assert_eq!(map.get(&key("a", 1)).unwrap().as_deref(), Some("old"));
+    thread::sleep(Duration::from_millis(100));
+
+    assert_eq!(map.get(&key("a", 1)).unwrap(), None);
+    assert_eq!(map.iter().unwrap().count(), 0);
+    assert_eq!(map.get_or_insert_with(&key("a", 1), || "new".to_owned()).unwrap(), "new");
+}
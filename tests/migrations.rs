@@ -0,0 +1,140 @@
+use std::fs;
+
+use file_backed_value::{FileBackedValue, FileBackedValueBuilder, FileBackedValueError, LockPolicy};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    email: String,
+}
+
+fn builder(dir: &tempfile::TempDir) -> FileBackedValueBuilder {
+    FileBackedValue::builder("user").dir(dir.path())
+}
+
+/// Upgrades a user from a plain name to a name with an email address.
+fn add_email(value: Value) -> Value {
+    json!({ "name": value, "email": "" })
+}
+
+fn stored_schema(value: &FileBackedValue) -> u64 {
+    let envelope: Value = serde_json::from_slice(&fs::read(value.path()).unwrap()).unwrap();
+    envelope["schema"].as_u64().unwrap()
+}
+
+#[test]
+fn older_value_is_migrated_and_rewritten_under_the_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir).build().unwrap().insert(&"alice").unwrap();
+
+    let value = builder(&dir).schema_version(1).migration(0, add_email)
+        .lock_policy(LockPolicy::PerOperation)
+        .build().unwrap();
+    let user = User { name: "alice".to_owned(), email: String::new() };
+    assert_eq!(value.get::<User>().unwrap(), Some(user));
+    assert_eq!(stored_schema(&value), 1);
+}
+
+#[test]
+fn older_value_is_migrated_without_rewriting_when_unlocked() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir).build().unwrap().insert(&"alice").unwrap();
+
+    let value = builder(&dir).schema_version(1).migration(0, add_email).build().unwrap();
+    let user = User { name: "alice".to_owned(), email: String::new() };
+    assert_eq!(value.get::<User>().unwrap(), Some(user));
+    assert_eq!(stored_schema(&value), 0);
+}
+
+#[test]
+fn migrations_are_chained() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir).build().unwrap().insert(&"alice").unwrap();
+
+    let value = builder(&dir).schema_version(2)
+        .migration(0, add_email)
+        .migration(1, |mut user| {
+            user["email"] = json!(format!("{}@example.com", user["name"].as_str().unwrap()));
+            user
+        })
+        .lock_policy(LockPolicy::PerOperation)
+        .build().unwrap();
+    let user = User { name: "alice".to_owned(), email: "alice@example.com".to_owned() };
+    assert_eq!(value.get::<User>().unwrap(), Some(user));
+    assert_eq!(stored_schema(&value), 2);
+}
+
+#[test]
+fn missing_step_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir).build().unwrap().insert(&"alice").unwrap();
+
+    let value = builder(&dir).schema_version(2).migration(1, |user| user).build().unwrap();
+    assert!(matches!(value.get::<User>(), Err(FileBackedValueError::MissingMigration(0))));
+}
+
+#[test]
+fn older_value_without_migrations_is_dirty() {
+    let dir = tempfile::tempdir().unwrap();
+    builder(&dir).build().unwrap().insert(&"alice").unwrap();
+
+    let value = builder(&dir).schema_version(1).build().unwrap();
+    assert_eq!(value.get::<User>().unwrap(), None);
+    let user = value.get_or_insert_with(|| User { name: "bob".to_owned(), email: String::new() }).unwrap();
+    assert_eq!(user.name, "bob");
+    assert_eq!(stored_schema(&value), 1);
+}
+
+#[test]
+fn newer_value_is_neither_read_nor_overwritten() {
+    let dir = tempfile::tempdir().unwrap();
+    let user = User { name: "alice".to_owned(), email: "alice@example.com".to_owned() };
+    builder(&dir).schema_version(1).build().unwrap().insert(&user).unwrap();
+
+    let without_migrations = builder(&dir).build().unwrap();
+    assert!(matches!(without_migrations.get::<String>(), Err(FileBackedValueError::NewerSchema(1))));
+    assert!(matches!(without_migrations.get_or_insert("bob".to_owned()), Err(FileBackedValueError::NewerSchema(1))));
+
+    let with_migrations = builder(&dir).schema_version(1).build().unwrap();
+    assert_eq!(with_migrations.get::<User>().unwrap(), Some(user));
+}
+
+#[cfg(feature = "bincode")]
+mod bincode {
+    use file_backed_value::{Bincode, CorruptionPolicy};
+
+    use super::*;
+
+    #[test]
+    fn newer_value_is_neither_read_nor_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = User { name: "alice".to_owned(), email: "alice@example.com".to_owned() };
+        let newer = builder(&dir).format(Bincode).schema_version(2).build().unwrap();
+        newer.insert(&user).unwrap();
+
+        let older = builder(&dir).format(Bincode).schema_version(1)
+            .corruption_policy(CorruptionPolicy::Recompute)
+            .build().unwrap();
+        assert!(matches!(older.get::<String>(), Err(FileBackedValueError::NewerSchema(2))));
+        assert!(matches!(older.get_or_insert("bob".to_owned()), Err(FileBackedValueError::NewerSchema(2))));
+        assert_eq!(newer.get::<User>().unwrap(), Some(user));
+    }
+
+    #[test]
+    fn older_value_is_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        builder(&dir).format(Bincode).build().unwrap().insert(&"alice").unwrap();
+
+        let value = builder(&dir).format(Bincode).schema_version(1).build().unwrap();
+        assert_eq!(value.get::<User>().unwrap(), None);
+    }
+
+    #[test]
+    fn migrations_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let res = builder(&dir).format(Bincode).schema_version(1).migration(0, add_email).build();
+        assert!(matches!(res, Err(FileBackedValueError::InvalidConfig(_))));
+    }
+}
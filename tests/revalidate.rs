@@ -0,0 +1,40 @@
+use std::{sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread, time::Duration};
+
+use file_backed_value::{FileBackedValue, MaybeStale};
+
+#[test]
+fn stale_value_is_refreshed_once_in_the_background() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(Duration::from_millis(500))
+        .stale_while_revalidate(Duration::from_secs(3600))
+        .build().unwrap();
+    value.insert(&1u32).unwrap();
+    thread::sleep(Duration::from_millis(600));
+
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    for _ in 0..100 {
+        let refreshes = Arc::clone(&refreshes);
+        let res = value.get_or_revalidate_with(move || {
+            refreshes.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(100));
+            2u32
+        }).unwrap();
+        assert_eq!(res, MaybeStale::Stale(1));
+    }
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    assert_eq!(value.get_or_revalidate_with(|| 3u32).unwrap(), MaybeStale::Fresh(2));
+}
+
+#[test]
+fn missing_value_is_computed_in_the_foreground() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(Duration::from_secs(60))
+        .stale_while_revalidate(Duration::from_secs(60))
+        .build().unwrap();
+
+    assert_eq!(value.get_or_revalidate_with(|| 1u32).unwrap(), MaybeStale::Fresh(1));
+}
@@ -0,0 +1,107 @@
+use std::{sync::{atomic::{AtomicUsize, Ordering}, Arc, Barrier}, thread, time::Duration};
+
+use file_backed_value::FileBackedValue;
+
+const THREADS: usize = 8;
+
+#[test]
+fn concurrent_computations_are_coalesced() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = Arc::new(FileBackedValue::builder("value").dir(dir.path()).build().unwrap());
+    let computations = Arc::new(AtomicUsize::new(0));
+    let barrier = Arc::new(Barrier::new(THREADS));
+
+    let handles: Vec<_> = (0..THREADS).map(|_| {
+        let value = Arc::clone(&value);
+        let computations = Arc::clone(&computations);
+        let barrier = Arc::clone(&barrier);
+        thread::spawn(move || {
+            barrier.wait();
+            value.get_or_insert_with(|| {
+                computations.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                42u32
+            }).unwrap()
+        })
+    }).collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 42);
+    }
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn separate_handles_to_the_same_path_are_coalesced() {
+    let dir = tempfile::tempdir().unwrap();
+    let computations = Arc::new(AtomicUsize::new(0));
+    let barrier = Arc::new(Barrier::new(THREADS));
+
+    let handles: Vec<_> = (0..THREADS).map(|_| {
+        let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+        let computations = Arc::clone(&computations);
+        let barrier = Arc::clone(&barrier);
+        thread::spawn(move || {
+            barrier.wait();
+            value.get_or_insert_with(|| {
+                computations.fetch_add(1, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                String::from("shared")
+            }).unwrap()
+        })
+    }).collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), "shared");
+    }
+    assert_eq!(computations.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn different_paths_are_computed_independently() {
+    let dir = tempfile::tempdir().unwrap();
+    let computations = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..THREADS).map(|i| {
+        let value = FileBackedValue::builder(&format!("value-{i}")).dir(dir.path()).build().unwrap();
+        let computations = Arc::clone(&computations);
+        thread::spawn(move || {
+            value.get_or_insert_with(|| {
+                computations.fetch_add(1, Ordering::SeqCst);
+                i
+            }).unwrap()
+        })
+    }).collect();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join().unwrap(), i);
+    }
+    assert_eq!(computations.load(Ordering::SeqCst), THREADS);
+}
+
+#[test]
+fn panicking_computation_does_not_poison_the_flight() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = Arc::new(FileBackedValue::builder("value").dir(dir.path()).build().unwrap());
+
+    let panicking = Arc::clone(&value);
+    let res = thread::spawn(move || {
+        panicking.get_or_insert_with::<_, u32>(|| panic!("computation failed"))
+    }).join();
+    assert!(res.is_err());
+
+    assert_eq!(value.get_or_insert_with(|| 7u32).unwrap(), 7);
+    assert_eq!(value.get::<u32>().unwrap(), Some(7));
+}
+
+#[test]
+fn value_is_shared_between_threads() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = Arc::new(FileBackedValue::builder("value").dir(dir.path()).build().unwrap());
+    value.insert(&1u32).unwrap();
+
+    let writer = Arc::clone(&value);
+    thread::spawn(move || writer.insert(&2u32).unwrap()).join().unwrap();
+
+    assert_eq!(value.get::<u32>().unwrap(), Some(2));
+}
@@ -0,0 +1,85 @@
+use std::{thread, time::Duration};
+
+use file_backed_value::{FileBackedValue, MaybeStale, TryInsertError};
+
+const DIRTY_TIME: Duration = Duration::from_millis(50);
+
+fn value(dir: &tempfile::TempDir, max_staleness: Duration) -> FileBackedValue {
+    FileBackedValue::builder("value").dir(dir.path())
+        .dirty_time(DIRTY_TIME)
+        .stale_if_error(max_staleness)
+        .build().unwrap()
+}
+
+fn fail() -> Result<u32, &'static str> {
+    Err("unavailable")
+}
+
+#[test]
+fn fresh_value_is_returned_without_computing() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, Duration::from_secs(3600));
+    value.insert(&1u32).unwrap();
+
+    assert!(matches!(value.get_or_try_refresh_with(fail), Ok(MaybeStale::Fresh(1))));
+}
+
+#[test]
+fn expired_value_is_refreshed() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, Duration::from_secs(3600));
+    value.insert(&1u32).unwrap();
+    thread::sleep(DIRTY_TIME * 2);
+
+    assert!(matches!(value.get_or_try_refresh_with(|| Ok::<_, &str>(2u32)), Ok(MaybeStale::Fresh(2))));
+    assert_eq!(value.get::<u32>().unwrap(), Some(2));
+}
+
+#[test]
+fn stale_value_is_returned_when_computing_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, Duration::from_secs(3600));
+    value.insert(&1u32).unwrap();
+    thread::sleep(DIRTY_TIME * 2);
+
+    assert!(matches!(value.get_or_try_refresh_with(fail), Ok(MaybeStale::Stale(1))));
+    // The stale value is not written again, so it stays expired.
+    assert_eq!(value.get::<u32>().unwrap(), None);
+}
+
+#[test]
+fn error_is_returned_past_the_maximum_staleness() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, DIRTY_TIME);
+    value.insert(&1u32).unwrap();
+    thread::sleep(DIRTY_TIME * 4);
+
+    assert!(matches!(value.get_or_try_refresh_with(fail), Err(TryInsertError::Compute("unavailable"))));
+}
+
+#[test]
+fn error_is_returned_without_a_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, Duration::from_secs(3600));
+
+    assert!(matches!(value.get_or_try_refresh_with(fail), Err(TryInsertError::Compute("unavailable"))));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn stale_value_is_returned_when_computing_fails_async() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = value(&dir, DIRTY_TIME * 4);
+    value.insert_async(&1u32).await.unwrap();
+    tokio::time::sleep(DIRTY_TIME * 2).await;
+
+    let res = value.get_or_try_refresh_with_async(|| async { fail() }).await;
+    assert!(matches!(res, Ok(MaybeStale::Stale(1))));
+
+    tokio::time::sleep(DIRTY_TIME * 6).await;
+    let res = value.get_or_try_refresh_with_async(|| async { fail() }).await;
+    assert!(matches!(res, Err(TryInsertError::Compute("unavailable"))));
+
+    let res = value.get_or_try_refresh_with_async(|| async { Ok::<_, &str>(2u32) }).await;
+    assert!(matches!(res, Ok(MaybeStale::Fresh(2))));
+}
@@ -0,0 +1,124 @@
+use std::{fs, thread, time::Duration};
+
+use file_backed_value::{EvictionPolicy, FileBackedStore, FileBackedValue, TypedFileBackedValue};
+
+fn value(store: &FileBackedStore, name: &str) -> FileBackedValue {
+    FileBackedValue::builder(name).store(store.clone()).build().unwrap()
+}
+
+#[test]
+fn least_recently_used_entry_is_evicted() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path()).max_entries(2);
+    let (a, b, c) = (value(&store, "a"), value(&store, "b"), value(&store, "c"));
+
+    a.insert(&1u32).unwrap();
+    thread::sleep(Duration::from_millis(5));
+    b.insert(&2u32).unwrap();
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(a.get::<u32>().unwrap(), Some(1));
+    thread::sleep(Duration::from_millis(5));
+    c.insert(&3u32).unwrap();
+
+    assert!(a.path().exists());
+    assert!(!b.path().exists());
+    assert!(c.path().exists());
+    assert_eq!(store.usage().unwrap().entries, 2);
+}
+
+#[test]
+fn least_frequently_used_entry_is_evicted() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path())
+        .max_entries(2)
+        .eviction(EvictionPolicy::LeastFrequentlyUsed);
+    let (a, b, c) = (value(&store, "a"), value(&store, "b"), value(&store, "c"));
+
+    a.insert(&1u32).unwrap();
+    b.insert(&2u32).unwrap();
+    for _ in 0..3 {
+        a.get::<u32>().unwrap();
+    }
+    b.get::<u32>().unwrap();
+    c.insert(&3u32).unwrap();
+
+    assert!(a.path().exists());
+    assert!(!b.path().exists());
+    assert!(c.path().exists());
+}
+
+#[test]
+fn entries_are_evicted_by_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path()).max_bytes(150);
+
+    for name in ["a", "b", "c"] {
+        value(&store, name).insert(&"x".repeat(50)).unwrap();
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    assert!(store.usage().unwrap().bytes <= 150);
+    assert!(!dir.path().join("a").exists());
+    assert!(dir.path().join("c").exists());
+}
+
+#[test]
+fn reads_do_not_rewrite_the_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path()).max_entries(10);
+    let index_path = dir.path().join(".store-index.json");
+
+    let mut typed: TypedFileBackedValue<u32> = value(&store, "typed").into();
+    typed.insert(1).unwrap();
+    let plain = value(&store, "plain");
+    plain.insert(&2u32).unwrap();
+    let index = fs::read(&index_path).unwrap();
+
+    for _ in 0..200 {
+        assert_eq!(typed.get().unwrap(), Some(&1));
+        assert_eq!(plain.get::<u32>().unwrap(), Some(2));
+    }
+    assert_eq!(fs::read(&index_path).unwrap(), index);
+
+    // The accesses are recorded once the store is updated.
+    store.evict().unwrap();
+    let index: serde_json::Value = serde_json::from_slice(&fs::read(&index_path).unwrap()).unwrap();
+    assert_eq!(index["entries"]["plain"]["accesses"], 201);
+    assert_eq!(index["entries"]["typed"]["accesses"], 201);
+}
+
+#[test]
+fn accesses_are_flushed_explicitly() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileBackedStore::new(dir.path());
+    let index_path = dir.path().join(".store-index.json");
+
+    let value = value(&store, "value");
+    value.insert(&1u32).unwrap();
+    value.get::<u32>().unwrap();
+    store.flush().unwrap();
+
+    let index: serde_json::Value = serde_json::from_slice(&fs::read(&index_path).unwrap()).unwrap();
+    assert_eq!(index["entries"]["value"]["accesses"], 2);
+}
+
+#[test]
+fn accesses_are_flushed_when_the_store_is_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let index_path = dir.path().join(".store-index.json");
+    value(&FileBackedStore::new(dir.path()), "value").insert(&1u32).unwrap();
+
+    // A short-lived process that only reads.
+    {
+        let store = FileBackedStore::new(dir.path());
+        let value = value(&store, "value");
+        for _ in 0..3 {
+            value.get::<u32>().unwrap();
+        }
+        let index: serde_json::Value = serde_json::from_slice(&fs::read(&index_path).unwrap()).unwrap();
+        assert_eq!(index["entries"]["value"]["accesses"], 1);
+    }
+
+    let index: serde_json::Value = serde_json::from_slice(&fs::read(&index_path).unwrap()).unwrap();
+    assert_eq!(index["entries"]["value"]["accesses"], 4);
+}
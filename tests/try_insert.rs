@@ -0,0 +1,41 @@
+use std::{error::Error, io};
+
+use file_backed_value::{FileBackedValue, FileBackedValueError, TryInsertError};
+
+#[test]
+fn failed_computation_writes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    let res = value.get_or_try_insert_with(|| Err::<u32, _>(io::Error::other("offline")));
+    assert!(matches!(res, Err(TryInsertError::Compute(_))));
+    assert!(!value.path().exists());
+}
+
+#[test]
+fn errors_convert_into_boxed_errors() {
+    fn compute(value: &FileBackedValue) -> Result<u32, Box<dyn Error>> {
+        Ok(value.get_or_try_insert_with(|| Err::<u32, _>(io::Error::other("offline")))?)
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+
+    let e = compute(&value).unwrap_err();
+    assert_eq!(e.to_string(), "failed to compute the value");
+    assert_eq!(e.source().unwrap().to_string(), "offline");
+}
+
+#[test]
+fn storage_errors_expose_their_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let value = FileBackedValue::builder("value").dir(dir.path()).build().unwrap();
+    std::fs::write(value.path(), b"not json").unwrap();
+
+    let e = value.get_or_try_insert_with(|| Ok::<u32, io::Error>(1)).unwrap_err();
+    let TryInsertError::Storage(storage) = &e else {
+        panic!("expected a storage error, got {e:?}");
+    };
+    assert!(matches!(storage, FileBackedValueError::JsonError(_)));
+    assert!(e.source().unwrap().source().is_some());
+}
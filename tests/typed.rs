@@ -0,0 +1,55 @@
+use std::{fs, time::{Duration, SystemTime}};
+
+use file_backed_value::{FileBackedValue, TypedFileBackedValue};
+
+fn typed(dir: &tempfile::TempDir) -> TypedFileBackedValue<String> {
+    TypedFileBackedValue::new_at("value", dir.path())
+}
+
+#[test]
+fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut value = typed(&dir);
+
+    assert_eq!(value.get().unwrap(), None);
+    assert_eq!(value.get_or_insert("default".to_owned()).unwrap(), "default");
+    value.insert("inserted".to_owned()).unwrap();
+    assert_eq!(value.get().unwrap().map(String::as_str), Some("inserted"));
+    assert_eq!(typed(&dir).get().unwrap().map(String::as_str), Some("inserted"));
+}
+
+#[test]
+fn external_writes_are_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut value = typed(&dir);
+    let other = FileBackedValue::new_at("value", dir.path());
+    value.insert("first".to_owned()).unwrap();
+    assert_eq!(value.get().unwrap().map(String::as_str), Some("first"));
+
+    // A write that changes the size of the file.
+    other.insert(&"much longer").unwrap();
+    assert_eq!(value.get().unwrap().map(String::as_str), Some("much longer"));
+
+    // A write that keeps the size of the file, but changes its modification time.
+    other.insert(&"much later!").unwrap();
+    let modified = SystemTime::now() + Duration::from_secs(10);
+    fs::File::options().write(true).open(value.path()).unwrap().set_modified(modified).unwrap();
+    assert_eq!(value.get().unwrap().map(String::as_str), Some("much later!"));
+
+    // Removing the file.
+    other.clear().unwrap();
+    assert_eq!(value.get().unwrap(), None);
+}
+
+#[test]
+fn cached_value_expires() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut value = typed(&dir);
+    value.set_dirty_time(Duration::from_millis(50));
+
+    value.insert("value".to_owned()).unwrap();
+    assert!(value.get().unwrap().is_some());
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(value.get().unwrap(), None);
+    assert_eq!(value.get_or_insert_with(|| "recomputed".to_owned()).unwrap(), "recomputed");
+}
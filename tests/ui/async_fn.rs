@@ -0,0 +1,8 @@
+use file_backed_value::file_backed;
+
+#[file_backed]
+async fn fetch(id: u32) -> u32 {
+    id
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use file_backed_value::file_backed;
+
+struct Client;
+
+impl Client {
+    #[file_backed]
+    fn fetch(&self, id: u32) -> u32 {
+        id
+    }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use file_backed_value::file_backed;
+
+#[file_backed]
+fn sum((a, b): (u32, u32)) -> u32 {
+    a + b
+}
+
+fn main() {}